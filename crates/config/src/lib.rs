@@ -11,43 +11,584 @@ use std::collections::{BTreeMap, BTreeSet};
 pub struct Config {
     pub version: u32,
     pub project: String,
+    /// Timeout applied to steps that set none themselves (and whose profile sets none).
+    #[serde(default)]
+    pub default_timeout_seconds: Option<u64>,
+    #[serde(default)]
+    pub images: ImagesSettings,
+    /// Format string for cache namespaces, e.g. `"{prefix}-{project}-{job}-{env8}"`.
+    /// Unset keeps the built-in `podci_<project>_<job>_<env12>` scheme.
+    #[serde(default)]
+    pub namespace_template: Option<String>,
+    /// Regexes whose matches are replaced with `***` in captured step output and errors.
+    #[serde(default)]
+    pub redact: Vec<String>,
     pub profiles: BTreeMap<String, Profile>,
     pub jobs: BTreeMap<String, Job>,
 }
 
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ImagesSettings {
+    #[serde(default)]
+    pub tag_strategy: TagStrategy,
+}
+
+/// How locally built template images (`localhost/podci-<template>`) are tagged.
+///
+/// `version` (default) tags by podCI version, `content-hash` by a hash of the
+/// rendered Containerfile, and `fixed` always uses `:latest`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum TagStrategy {
+    #[default]
+    Version,
+    ContentHash,
+    Fixed,
+}
+
+impl TagStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TagStrategy::Version => "version",
+            TagStrategy::ContentHash => "content-hash",
+            TagStrategy::Fixed => "fixed",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Profile {
+    /// Parent profile to inherit `container` and `env` from (resolved at load time).
+    #[serde(default)]
+    pub extends: Option<String>,
+    /// Template name or image ref. May be omitted when inherited via `extends`.
+    #[serde(default)]
     pub container: String,
     #[serde(default)]
     pub env: BTreeMap<String, String>,
+    /// `.env`-style file (`KEY=VALUE` lines, `#` comments), relative to the config's
+    /// directory. Merged under `env` when the config loads, so its contents feed `env_id`.
+    #[serde(default)]
+    pub env_file: Option<String>,
+    /// Keys of `env` whose value comes from an `env_file` (this profile's or, via
+    /// `extends`, a parent's) rather than inline `env`. Filled when the config loads;
+    /// these values are treated like `run --env-file` ones: never printed or recorded.
+    #[serde(skip)]
+    pub env_file_keys: BTreeSet<String>,
+    /// Default timeout for steps run under this profile.
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+    /// Default repo-relative workdir for steps that set none.
+    #[serde(default)]
+    pub workdir: Option<String>,
+    /// Expose the host's `~/.gitconfig` (read-only snapshot) to steps. Not part of `env_id`.
+    #[serde(default)]
+    pub mount_gitconfig: bool,
+    /// Cargo layout inside the image (`[profiles.<name>.cargo]`).
+    #[serde(default)]
+    pub cargo: CargoSettings,
+    /// Per-cache settings keyed by built-in cache kind (see [`CACHE_KINDS`]).
+    #[serde(default)]
+    pub caches: BTreeMap<String, CacheSettings>,
+    /// Extra bind mounts (`host:container[:opts]`), e.g. a shared ccache directory.
+    /// Relative host paths are resolved against the repo root. Not part of `env_id`.
+    #[serde(default)]
+    pub mounts: Vec<String>,
+    /// CPU limit passed to `podman run --cpus` (e.g. `"2"`, `"1.5"`). Part of `env_id`.
+    #[serde(default)]
+    pub cpus: Option<String>,
+    /// Memory limit passed to `podman run --memory` (e.g. `"4g"`). Part of `env_id`.
+    #[serde(default)]
+    pub memory: Option<String>,
+    /// Container hostname (`podman run --hostname`) for steps that set none.
+    #[serde(default)]
+    pub hostname: Option<String>,
+}
+
+/// `CARGO_HOME` used by the podCI rust-* templates.
+pub const DEFAULT_CARGO_HOME: &str = "/usr/local/cargo";
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CargoSettings {
+    /// Set to `false` for non-Rust images: no cargo cache mounts, no `CARGO_HOME`.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// `CARGO_HOME` inside the container; the registry/git caches mount under it.
+    #[serde(default)]
+    pub home: Option<String>,
+}
+
+impl Default for CargoSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            home: None,
+        }
+    }
+}
+
+/// `sh -c ...` / `bash -c ...` style argv.
+fn is_shell_c(run: &[String]) -> bool {
+    let [shell, flag, ..] = run else {
+        return false;
+    };
+    let base = shell.rsplit('/').next().unwrap_or(shell);
+    matches!(base, "sh" | "bash" | "dash" | "zsh") && flag == "-c"
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Built-in cache volumes podCI mounts into every step container.
+pub const CACHE_KINDS: [&str; 3] = ["cargo_registry", "cargo_git", "target"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CacheSettings {
+    #[serde(default)]
+    pub cache_scope: CacheScope,
+}
+
+/// How a cache volume is keyed.
+///
+/// `env` (default) scopes the volume to the full `env_id`, so any change to a
+/// build-affecting input starts from a cold cache. `profile` keys the volume by
+/// project + profile only, sharing it across jobs and env_ids; this trades
+/// isolation for warm caches, so stale or incompatible outputs from another
+/// environment may be visible to the build.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheScope {
+    #[default]
+    Env,
+    Profile,
 }
 
+/// A parsed `profiles.<name>.mounts` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileMount {
+    /// Absolute or repo-relative host path.
+    pub host: String,
+    /// Absolute container path.
+    pub container: String,
+    /// Mount options passed through to podman (`ro`, `z`, `Z`, ...).
+    pub options: Option<String>,
+}
+
+/// Container paths owned by podCI; user mounts may not cover them, anything below, or an ancestor.
+const PROTECTED_MOUNT_PATHS: [&str; 2] = ["/work", DEFAULT_CARGO_HOME];
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Job {
     pub profile: String,
     pub step_order: Vec<String>,
     pub steps: BTreeMap<String, Step>,
+    /// Axes to run the job across (`[jobs.<name>.matrix]`), one run per combination.
+    /// `profile` and `container` select those; any other axis is set as a step env var.
+    #[serde(default)]
+    pub matrix: BTreeMap<String, Vec<String>>,
+    /// Default repo-relative workdir for this job's steps that set none; overrides the
+    /// profile's.
+    #[serde(default)]
+    pub workdir: Option<String>,
+}
+
+/// Matrix axes that select the profile/container instead of setting an env var.
+pub const MATRIX_PROFILE_AXIS: &str = "profile";
+pub const MATRIX_CONTAINER_AXIS: &str = "container";
+
+/// One combination of a job's matrix (see [`Config::expand_matrix`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedJob {
+    pub job: String,
+    /// Axis values of this combination; empty for a job without a matrix.
+    pub axes: BTreeMap<String, String>,
+}
+
+impl ResolvedJob {
+    /// Display name, e.g. `test[container=rust-alpine,toolchain=stable]`.
+    pub fn display_name(&self) -> String {
+        if self.axes.is_empty() {
+            return self.job.clone();
+        }
+        let axes: Vec<String> = self.axes.iter().map(|(k, v)| format!("{k}={v}")).collect();
+        format!("{}[{}]", self.job, axes.join(","))
+    }
+}
+
+/// One step of a run plan: what `podci run` would execute, resolved against the profile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedStep {
+    pub name: String,
+    pub run: Vec<String>,
+    pub profile: String,
+    pub container: String,
+    /// Effective repo-relative workdir (`None`: repo root).
+    pub workdir: Option<String>,
+    /// Effective timeout (see [`Config::effective_timeout_seconds`]).
+    pub timeout_seconds: Option<u64>,
+    /// Why the step will not execute, if it won't.
+    pub skip_reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Step {
     pub run: Vec<String>,
+    /// Human-readable description shown next to the step name. Cosmetic; not part of `env_id`.
+    #[serde(default)]
+    pub description: Option<String>,
     #[serde(default)]
     pub workdir: Option<String>,
     #[serde(default)]
     pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+    /// Non-zero exit codes that still fail the step (and the run) but let later steps run.
+    #[serde(default)]
+    pub continue_on: Vec<i32>,
+    /// A failure (non-zero exit or timeout) is recorded but neither stops the job nor
+    /// fails the run.
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// Re-run the step up to this many extra times while it exits non-zero.
+    #[serde(default)]
+    pub retries: Option<u32>,
+    /// Seconds to wait between retry attempts (default: none).
+    #[serde(default)]
+    pub retry_backoff_secs: Option<u64>,
+    /// Steps of the same job that must run before this one. `step_order` breaks ties.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Temporarily disable the step: it stays in `step_order` but is never executed.
+    #[serde(default)]
+    pub skip: bool,
+    /// Container hostname (`podman run --hostname`); overrides the profile's.
+    #[serde(default)]
+    pub hostname: Option<String>,
+}
+
+impl Job {
+    /// Effective repo-relative workdir of `step` run under `profile`. Precedence:
+    /// step > job > profile > repo root (`None`).
+    pub fn effective_workdir<'a>(
+        &'a self,
+        profile: &'a Profile,
+        step: &'a Step,
+    ) -> Option<&'a str> {
+        step.workdir
+            .as_deref()
+            .or(self.workdir.as_deref())
+            .or(profile.workdir.as_deref())
+    }
+
+    /// Steps in execution order: `step_order`, reordered so every step runs after its
+    /// `depends_on`. Among steps whose dependencies are met, the earliest in `step_order`
+    /// runs first, so a job without `depends_on` runs exactly in `step_order`.
+    pub fn execution_order(&self) -> Result<Vec<String>> {
+        let mut done: BTreeSet<&str> = BTreeSet::new();
+        let mut pending: Vec<&str> = self.step_order.iter().map(String::as_str).collect();
+        let mut out = Vec::with_capacity(pending.len());
+        while !pending.is_empty() {
+            let ready = pending.iter().position(|name| {
+                self.steps
+                    .get(*name)
+                    .is_none_or(|st| st.depends_on.iter().all(|d| done.contains(d.as_str())))
+            });
+            let Some(i) = ready else {
+                bail!(
+                    "depends_on cycle: {}",
+                    self.dependency_cycle(&pending).join(" -> ")
+                );
+            };
+            let name = pending.remove(i);
+            done.insert(name);
+            out.push(name.to_string());
+        }
+        Ok(out)
+    }
+
+    /// A cycle among `pending` steps, each of which has an unmet dependency in `pending`.
+    /// The first step is repeated at the end (`a -> b -> a`).
+    fn dependency_cycle<'a>(&'a self, pending: &[&'a str]) -> Vec<&'a str> {
+        let mut path: Vec<&str> = vec![pending[0]];
+        loop {
+            let cur = path[path.len() - 1];
+            let next = self.steps[cur]
+                .depends_on
+                .iter()
+                .map(String::as_str)
+                .find(|d| pending.contains(d))
+                .expect("pending steps always have a pending dependency");
+            if let Some(start) = path.iter().position(|p| *p == next) {
+                let mut cycle = path.split_off(start);
+                cycle.push(next);
+                return cycle;
+            }
+            path.push(next);
+        }
+    }
+}
+
+impl Step {
+    /// Whether a step that exited with `exit_code` lets the run continue.
+    pub fn continues_on(&self, exit_code: i32) -> bool {
+        exit_code != 0 && self.continue_on.contains(&exit_code)
+    }
+}
+
+/// Where an env key is defined in the config.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EnvKeyLocation {
+    Profile { profile: String },
+    ProfileEnvFile { profile: String },
+    Step { job: String, step: String },
+}
+
+impl std::fmt::Display for EnvKeyLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvKeyLocation::Profile { profile } => write!(f, "profiles.{profile}.env"),
+            EnvKeyLocation::ProfileEnvFile { profile } => {
+                write!(f, "profiles.{profile}.env_file")
+            }
+            EnvKeyLocation::Step { job, step } => write!(f, "jobs.{job}.steps.{step}.env"),
+        }
+    }
+}
+
+/// One definition of an env key (see [`Config::env_key_inventory`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvKeyDefinition {
+    pub location: EnvKeyLocation,
+    pub value: String,
+}
+
+/// Config values overridden from the command line for a single run.
+///
+/// All fields are build-affecting and therefore change `env_id`:
+/// - `profile` (`--profile`) replaces the job's profile (container, env, caches).
+/// - `container` (`--container`) replaces the selected profile's container, whether it
+///   names a template or an explicit image ref. Callers validate the reference.
+/// - `default_timeout_seconds` (`--default-timeout`) replaces the top-level default,
+///   which feeds the effective timeout of steps with no step/profile timeout.
+/// - `env` (`--env KEY=VALUE`) is set on every step of the job, so it wins over
+///   profile and step env; each distinct set gets its own fingerprint.
+///
+/// Run-mode flags (`--dry-run`, `--pull`, `--rebuild`, `--state-dir`, ...) are not
+/// config overrides and never affect `env_id`.
+#[derive(Debug, Clone, Default)]
+pub struct CliOverrides {
+    pub profile: Option<String>,
+    pub container: Option<String>,
+    pub default_timeout_seconds: Option<u64>,
+    pub env: Vec<(String, String)>,
+}
+
+/// A non-fatal config concern. `code` is stable and meant for filtering by tooling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigWarning {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
 }
 
 impl Config {
+    /// Parse and validate a config; `env_file` paths resolve against the current directory.
     pub fn from_toml_str(s: &str) -> Result<Self> {
-        let cfg: Config = toml::from_str(s).context("parse podci.toml")?;
+        Self::from_toml_str_in(s, std::path::Path::new("."))
+    }
+
+    /// Like [`Config::from_toml_str`], resolving `env_file` paths against `config_dir`
+    /// (the directory holding podci.toml).
+    pub fn from_toml_str_in(s: &str, config_dir: &std::path::Path) -> Result<Self> {
+        let mut cfg: Config = toml::from_str(s).context("parse podci.toml")?;
+        cfg.load_env_files(config_dir)?;
+        cfg.resolve_profile_extends()?;
+        cfg.normalize_container_refs();
         cfg.validate()?;
         Ok(cfg)
     }
 
+    /// Like [`Config::from_toml_str_in`], plus non-fatal warnings about the config.
+    pub fn from_toml_str_with_warnings(
+        s: &str,
+        config_dir: &std::path::Path,
+    ) -> Result<(Self, Vec<ConfigWarning>)> {
+        let cfg = Self::from_toml_str_in(s, config_dir)?;
+        // Re-read as a raw table: "env = {}" and an absent env both deserialize to an empty map.
+        let raw: toml::Table = toml::from_str(s).context("parse podci.toml")?;
+        let warnings = cfg.collect_warnings(&raw);
+        Ok((cfg, warnings))
+    }
+
+    /// `missing-workdir` warnings for steps whose effective workdir does not exist under
+    /// `repo_root` (the check `podci run` does per step, done upfront). Warnings rather
+    /// than errors: an earlier step may create the directory.
+    pub fn workdir_warnings(&self, repo_root: &std::path::Path) -> Vec<ConfigWarning> {
+        let mut out = Vec::new();
+        for (job_name, job) in &self.jobs {
+            let Some(profile) = self.profiles.get(&job.profile) else {
+                continue;
+            };
+            for step_name in &job.step_order {
+                let Some(wd) = job.effective_workdir(profile, &job.steps[step_name]) else {
+                    continue;
+                };
+                if !repo_root.join(wd).is_dir() {
+                    out.push(ConfigWarning {
+                        code: "missing-workdir",
+                        message: format!(
+                            "job '{job_name}' step '{step_name}' workdir '{wd}' does not exist under {}",
+                            repo_root.display()
+                        ),
+                    });
+                }
+            }
+        }
+        out
+    }
+
+    fn collect_warnings(&self, raw: &toml::Table) -> Vec<ConfigWarning> {
+        let mut out = Vec::new();
+
+        for (job_name, job) in &self.jobs {
+            for step_name in &job.step_order {
+                let run = &job.steps[step_name].run;
+                if is_shell_c(run) {
+                    out.push(ConfigWarning {
+                        code: "shell-step",
+                        message: format!(
+                            "job '{job_name}' step '{step_name}' runs via '{} -c'; argv steps are clearer and quote-safe",
+                            run[0]
+                        ),
+                    });
+                }
+            }
+        }
+
+        let empty_env = |t: Option<&toml::Value>| {
+            t.and_then(|v| v.get("env"))
+                .and_then(|e| e.as_table())
+                .is_some_and(|e| e.is_empty())
+        };
+        for profile_name in self.profiles.keys() {
+            let raw_profile = raw.get("profiles").and_then(|p| p.get(profile_name));
+            if empty_env(raw_profile) {
+                out.push(ConfigWarning {
+                    code: "empty-env",
+                    message: format!("profile '{profile_name}' has an empty env table"),
+                });
+            }
+        }
+        for (job_name, job) in &self.jobs {
+            for step_name in job.steps.keys() {
+                let raw_step = raw
+                    .get("jobs")
+                    .and_then(|j| j.get(job_name))
+                    .and_then(|j| j.get("steps"))
+                    .and_then(|st| st.get(step_name));
+                if empty_env(raw_step) {
+                    out.push(ConfigWarning {
+                        code: "empty-env",
+                        message: format!(
+                            "job '{job_name}' step '{step_name}' has an empty env table"
+                        ),
+                    });
+                }
+            }
+        }
+
+        // A profile is used when a job or a matrix `profile` axis selects it, or when a
+        // used profile extends it (directly or through other parents).
+        let mut used: BTreeSet<&str> = BTreeSet::new();
+        for job in self.jobs.values() {
+            let axis = job.matrix.get(MATRIX_PROFILE_AXIS).into_iter().flatten();
+            for name in std::iter::once(&job.profile).chain(axis) {
+                let mut next = Some(name.as_str());
+                while let Some(n) = next.filter(|n| used.insert(n)) {
+                    next = self.profiles.get(n).and_then(|p| p.extends.as_deref());
+                }
+            }
+        }
+        for profile_name in self.profiles.keys() {
+            if !used.contains(profile_name.as_str()) {
+                out.push(ConfigWarning {
+                    code: "unused-profile",
+                    message: format!("profile '{profile_name}' is not referenced by any job"),
+                });
+            }
+        }
+
+        out
+    }
+
+    /// Merge each profile's `env_file` under its inline `env` (inline wins per key). Runs
+    /// before `extends` is resolved, so a child inherits its parent's file values too.
+    fn load_env_files(&mut self, config_dir: &std::path::Path) -> Result<()> {
+        for (profile_name, profile) in &mut self.profiles {
+            let Some(file) = &profile.env_file else {
+                continue;
+            };
+            if file.starts_with('/') || file.contains("..") {
+                bail!(
+                    "profile '{profile_name}' env_file must be relative to the config without '..' (got '{file}')"
+                );
+            }
+            let path = config_dir.join(file);
+            let text = std::fs::read_to_string(&path).with_context(|| {
+                format!("profile '{profile_name}' env_file: read {}", path.display())
+            })?;
+            let mut env = parse_env_file(&text)
+                .with_context(|| format!("profile '{profile_name}' env_file {}", path.display()))?;
+            profile.env_file_keys = env
+                .keys()
+                .filter(|k| !profile.env.contains_key(*k))
+                .cloned()
+                .collect();
+            env.append(&mut profile.env);
+            profile.env = env;
+        }
+        Ok(())
+    }
+
+    /// Replace every profile with its effective profile: `container` falls back to the
+    /// parent's, and `env` is the parent's merged with the child's (child wins per key).
+    /// Other fields are not inherited.
+    fn resolve_profile_extends(&mut self) -> Result<()> {
+        let mut resolved = BTreeMap::new();
+        for name in self.profiles.keys() {
+            resolve_profile(&self.profiles, name, &mut resolved, &mut Vec::new())?;
+        }
+        self.profiles = resolved;
+        Ok(())
+    }
+
+    /// Lowercase the repository part of explicit image refs in profiles and matrix
+    /// `container` axes (see [`normalize_container_ref`]).
+    fn normalize_container_refs(&mut self) {
+        for profile in self.profiles.values_mut() {
+            profile.container = normalize_container_ref(&profile.container);
+        }
+        for job in self.jobs.values_mut() {
+            if let Some(values) = job.matrix.get_mut(MATRIX_CONTAINER_AXIS) {
+                for v in values.iter_mut() {
+                    *v = normalize_container_ref(v);
+                }
+            }
+        }
+    }
+
     pub fn validate(&self) -> Result<()> {
         if self.version != 1 {
             bail!("unsupported config version {} (expected 1)", self.version);
@@ -61,6 +602,61 @@ impl Config {
         if self.jobs.is_empty() {
             bail!("jobs must be non-empty");
         }
+        if self.default_timeout_seconds == Some(0) {
+            bail!("default_timeout_seconds must be greater than 0");
+        }
+
+        for (profile_name, profile) in &self.profiles {
+            if profile.container.trim().is_empty() {
+                bail!("profile '{profile_name}' has no container (set `container` or `extends`)");
+            }
+            if let Err(e) = classify_container_ref(&profile.container) {
+                bail!("profile '{profile_name}' {e}");
+            }
+            if profile.timeout_seconds == Some(0) {
+                bail!("profile '{profile_name}' timeout_seconds must be greater than 0");
+            }
+            if let Some(wd) = &profile.workdir {
+                if wd.starts_with('/') || wd.contains("..") {
+                    bail!(
+                        "profile '{profile_name}' workdir must be relative to the repo without '..' (got '{wd}')"
+                    );
+                }
+            }
+            if let Some(home) = &profile.cargo.home {
+                if !home.starts_with('/') || home.split('/').any(|c| c == "..") {
+                    bail!(
+                        "profile '{profile_name}' cargo.home must be an absolute container path without '..' (got '{home}')"
+                    );
+                }
+            }
+            // The value format is podman's to check; only reject what would garble argv.
+            for (key, value) in [("cpus", &profile.cpus), ("memory", &profile.memory)] {
+                if let Some(v) = value {
+                    if v.is_empty() || !v.chars().all(|c| c.is_ascii_graphic()) {
+                        bail!(
+                            "profile '{profile_name}' {key} must be non-empty ASCII without whitespace (got '{v}')"
+                        );
+                    }
+                }
+            }
+            if let Some(h) = &profile.hostname {
+                if !is_dns_label(h) {
+                    bail!("profile '{profile_name}' hostname must be a DNS label (got '{h}')");
+                }
+            }
+            if let Err(e) = profile.parsed_mounts() {
+                bail!("profile '{profile_name}' {e}");
+            }
+            for kind in profile.caches.keys() {
+                if !CACHE_KINDS.contains(&kind.as_str()) {
+                    bail!(
+                        "profile '{profile_name}' configures unknown cache '{kind}' (expected one of: {})",
+                        CACHE_KINDS.join(", ")
+                    );
+                }
+            }
+        }
 
         for (job_name, job) in &self.jobs {
             if !self.profiles.contains_key(&job.profile) {
@@ -69,7 +665,15 @@ impl Config {
                     job.profile
                 );
             }
+            if let Some(wd) = &job.workdir {
+                if wd.starts_with('/') || wd.contains("..") {
+                    bail!(
+                        "job '{job_name}' workdir must be relative to the repo without '..' (got '{wd}')"
+                    );
+                }
+            }
             validate_step_order(job_name, job)?;
+            validate_matrix(job_name, job, &self.profiles)?;
         }
 
         Ok(())
@@ -86,81 +690,665 @@ impl Config {
             .get(name)
             .ok_or_else(|| anyhow!("unknown profile '{name}'"))
     }
-}
 
-fn validate_step_order(job_name: &str, job: &Job) -> Result<()> {
-    if job.step_order.is_empty() {
-        if !job.steps.is_empty() {
-            bail!("job '{job_name}' has steps but empty step_order");
+    /// Apply CLI overrides for a run of `job`. Call before computing `env_id` so the
+    /// fingerprint sees the effective values.
+    pub fn apply_overrides(&mut self, job: &str, overrides: &CliOverrides) -> Result<()> {
+        self.job(job)?;
+        if let Some(profile) = &overrides.profile {
+            self.profile(profile)?;
+            if let Some(j) = self.jobs.get_mut(job) {
+                j.profile = profile.clone();
+            }
         }
-        return Ok(());
+        if let Some(container) = &overrides.container {
+            let profile = self.jobs[job].profile.clone();
+            if let Some(p) = self.profiles.get_mut(&profile) {
+                p.container = normalize_container_ref(container);
+            }
+        }
+        if overrides.default_timeout_seconds.is_some() {
+            self.default_timeout_seconds = overrides.default_timeout_seconds;
+        }
+        if !overrides.env.is_empty() {
+            if let Some(j) = self.jobs.get_mut(job) {
+                for step in j.steps.values_mut() {
+                    step.env.extend(overrides.env.iter().cloned());
+                }
+            }
+        }
+        Ok(())
     }
 
-    let mut seen = BTreeSet::new();
-    for s in &job.step_order {
-        if !seen.insert(s.clone()) {
-            bail!("job '{job_name}' step_order contains duplicate step '{s}'");
+    /// Every combination of `job`'s matrix, in axis-name then value order. A job
+    /// without a matrix yields one combination with no axes.
+    pub fn expand_matrix(&self, job: &str) -> Result<Vec<ResolvedJob>> {
+        let mut combos = vec![BTreeMap::new()];
+        for (axis, values) in &self.job(job)?.matrix {
+            combos = combos
+                .into_iter()
+                .flat_map(|c: BTreeMap<String, String>| {
+                    values.iter().map(move |v| {
+                        let mut c = c.clone();
+                        c.insert(axis.clone(), v.clone());
+                        c
+                    })
+                })
+                .collect();
         }
-        if !job.steps.contains_key(s) {
-            bail!("job '{job_name}' step_order references missing step '{s}'");
+        Ok(combos
+            .into_iter()
+            .map(|axes| ResolvedJob {
+                job: job.to_string(),
+                axes,
+            })
+            .collect())
+    }
+
+    /// Add ad-hoc matrix axes (`podci run --matrix`) to `job`, replacing config axes
+    /// of the same name, and validate the result like a config-defined matrix.
+    pub fn extend_matrix(&mut self, job: &str, axes: &[(String, Vec<String>)]) -> Result<()> {
+        self.job(job)?;
+        let Some(j) = self.jobs.get_mut(job) else {
+            return Ok(());
+        };
+        for (axis, values) in axes {
+            j.matrix.insert(axis.clone(), values.clone());
         }
+        validate_matrix(job, &self.jobs[job], &self.profiles)
     }
 
-    // No steps outside step_order (prevents hidden drift)
-    let order: BTreeSet<_> = job.step_order.iter().cloned().collect();
-    let extras: Vec<_> = job
-        .steps
-        .keys()
-        .filter(|k| !order.contains(*k))
-        .cloned()
-        .collect();
-    if !extras.is_empty() {
-        bail!(
-            "job '{job_name}' has steps not listed in step_order: {:?}",
-            extras
-        );
+    /// Apply one matrix combination for a run of `job`: `profile`/`container` axes
+    /// select those, other axes are added to the profile env (step env still wins).
+    /// Call before computing `env_id`.
+    pub fn apply_matrix(&mut self, job: &str, axes: &BTreeMap<String, String>) -> Result<()> {
+        self.job(job)?;
+        if let Some(profile) = axes.get(MATRIX_PROFILE_AXIS) {
+            self.profile(profile)?;
+            if let Some(j) = self.jobs.get_mut(job) {
+                j.profile = profile.clone();
+            }
+        }
+        let profile = self.jobs[job].profile.clone();
+        if let Some(p) = self.profiles.get_mut(&profile) {
+            for (axis, value) in axes {
+                match axis.as_str() {
+                    MATRIX_PROFILE_AXIS => {}
+                    MATRIX_CONTAINER_AXIS => p.container = normalize_container_ref(value),
+                    _ => {
+                        p.env.insert(axis.clone(), value.clone());
+                        p.env_file_keys.remove(axis);
+                    }
+                }
+            }
+        }
+        Ok(())
     }
 
-    // Basic sanity: each step must have a non-empty argv
-    for (step_name, step) in &job.steps {
-        if step.run.is_empty() {
-            bail!("job '{job_name}' step '{step_name}' has empty run argv");
+    /// Expand `${env:NAME}` / `${env:NAME:-default}` in the env values of `job`'s steps
+    /// and its profile, using `lookup` for host variables. Call after overrides and
+    /// before computing `env_id`, so the fingerprint sees the resolved values.
+    pub fn expand_host_env(
+        &mut self,
+        job: &str,
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<()> {
+        let profile_name = self.job(job)?.profile.clone();
+        if let Some(p) = self.profiles.get_mut(&profile_name) {
+            // `env_file` values are verbatim: no `${env:...}` expansion.
+            for (k, v) in p
+                .env
+                .iter_mut()
+                .filter(|(k, _)| !p.env_file_keys.contains(*k))
+            {
+                *v = interpolate_host_env(v, lookup)
+                    .with_context(|| format!("profiles.{profile_name}.env.{k}"))?;
+            }
         }
+        if let Some(j) = self.jobs.get_mut(job) {
+            for (step_name, step) in j.steps.iter_mut() {
+                for (k, v) in step.env.iter_mut() {
+                    *v = interpolate_host_env(v, lookup)
+                        .with_context(|| format!("jobs.{job}.steps.{step_name}.env.{k}"))?;
+                }
+            }
+        }
+        Ok(())
     }
 
-    Ok(())
-}
+    /// Every user env key in the config with all places it is defined, profiles
+    /// first, then job steps (each in name order).
+    pub fn env_key_inventory(&self) -> BTreeMap<String, Vec<EnvKeyDefinition>> {
+        let mut out: BTreeMap<String, Vec<EnvKeyDefinition>> = BTreeMap::new();
+        let mut add = |env: &BTreeMap<String, String>, location: EnvKeyLocation| {
+            for (k, v) in env {
+                out.entry(k.clone()).or_default().push(EnvKeyDefinition {
+                    location: location.clone(),
+                    value: v.clone(),
+                });
+            }
+        };
+        for (name, profile) in &self.profiles {
+            let (from_file, inline): (BTreeMap<_, _>, BTreeMap<_, _>) = profile
+                .env
+                .clone()
+                .into_iter()
+                .partition(|(k, _)| profile.env_file_keys.contains(k));
+            add(
+                &inline,
+                EnvKeyLocation::Profile {
+                    profile: name.clone(),
+                },
+            );
+            add(
+                &from_file,
+                EnvKeyLocation::ProfileEnvFile {
+                    profile: name.clone(),
+                },
+            );
+        }
+        for (job_name, job) in &self.jobs {
+            for (step_name, step) in &job.steps {
+                add(
+                    &step.env,
+                    EnvKeyLocation::Step {
+                        job: job_name.clone(),
+                        step: step_name.clone(),
+                    },
+                );
+            }
+        }
+        out
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Effective step timeout. Precedence: step > profile > global default > none.
+    pub fn effective_timeout_seconds(&self, profile: &Profile, step: &Step) -> Option<u64> {
+        step.timeout_seconds
+            .or(profile.timeout_seconds)
+            .or(self.default_timeout_seconds)
+    }
 
-    #[test]
-    fn rejects_wrong_version() {
-        let s = r#"
-version = 2
-project = "x"
+    /// Ordered steps a run of `job` goes through (`step_order` adjusted for `depends_on`),
+    /// or just `step_only` when given: a selected step runs alone, without its dependencies.
+    ///
+    /// Pure: nothing is executed, so tooling can preview a run. Apply overrides and matrix
+    /// axes first to plan exactly what `podci run` would.
+    pub fn plan_steps(&self, job: &str, step_only: Option<&str>) -> Result<Vec<PlannedStep>> {
+        let names = match step_only {
+            Some(s) => vec![s.to_string()],
+            None => self.job(job)?.execution_order()?,
+        };
+        names.iter().map(|s| self.planned_step(job, s)).collect()
+    }
 
-[profiles.dev]
-container = "rust-debian"
+    /// Plan entry for a single step of `job` (see [`Config::plan_steps`]).
+    pub fn planned_step(&self, job: &str, step: &str) -> Result<PlannedStep> {
+        let j = self.job(job)?;
+        let Some(st) = j.steps.get(step) else {
+            bail!("unknown step '{step}' for job '{job}'");
+        };
+        let profile = self.profile(&j.profile)?;
+        Ok(PlannedStep {
+            name: step.to_string(),
+            run: st.run.clone(),
+            profile: j.profile.clone(),
+            container: profile.container.clone(),
+            workdir: j.effective_workdir(profile, st).map(str::to_string),
+            timeout_seconds: self.effective_timeout_seconds(profile, st),
+            skip_reason: st.skip.then(|| "skip = true".to_string()),
+        })
+    }
+}
 
-[jobs.default]
-profile = "dev"
-step_order = []
-steps = {}
-"#;
-        let err = Config::from_toml_str(s).unwrap_err();
-        assert!(err.to_string().contains("unsupported config version"));
+impl Profile {
+    /// Effective container hostname of `step`. Precedence: step > profile > podman's
+    /// generated one (`None`).
+    pub fn effective_hostname<'a>(&'a self, step: &'a Step) -> Option<&'a str> {
+        step.hostname.as_deref().or(self.hostname.as_deref())
     }
 
-    #[test]
-    fn enforces_step_order_refs() {
-        let s = r#"
-version = 1
-project = "x"
+    /// Effective `CARGO_HOME`, or `None` when cargo integration is disabled.
+    pub fn cargo_home(&self) -> Option<&str> {
+        if !self.cargo.enabled {
+            return None;
+        }
+        Some(self.cargo.home.as_deref().unwrap_or(DEFAULT_CARGO_HOME))
+    }
 
-[profiles.dev]
-container = "rust-debian"
+    /// Parse and check [`Profile::mounts`]. Host paths must not escape with `..`, and
+    /// container paths must not cover `/work` (repo and target cache) or `CARGO_HOME`, nor
+    /// shadow them by mounting over an ancestor such as `/usr/local`.
+    pub fn parsed_mounts(&self) -> Result<Vec<ProfileMount>> {
+        let is_under = |path: &str, root: &str| {
+            path == root || path.strip_prefix(root).is_some_and(|r| r.starts_with('/'))
+        };
+        let mut out = Vec::with_capacity(self.mounts.len());
+        for spec in &self.mounts {
+            let mut parts = spec.splitn(3, ':');
+            let host = parts.next().unwrap_or_default();
+            let (Some(container), options) = (parts.next(), parts.next()) else {
+                bail!("mount '{spec}' must be host:container[:opts]");
+            };
+            if host.is_empty() || host.split('/').any(|c| c == "..") {
+                bail!("mount '{spec}' host path must be absolute or repo-relative without '..'");
+            }
+            let container = container.trim_end_matches('/');
+            if !container.starts_with('/') || container.split('/').any(|c| c == "..") {
+                bail!("mount '{spec}' container path must be absolute without '..'");
+            }
+            let protected = PROTECTED_MOUNT_PATHS
+                .iter()
+                .copied()
+                .chain(self.cargo_home());
+            for root in protected {
+                let root_path = root.trim_end_matches('/');
+                if is_under(container, root_path) || is_under(root_path, container) {
+                    bail!("mount '{spec}' must not cover podCI-managed '{root}'");
+                }
+            }
+            if options.is_some_and(|o| o.is_empty() || o.contains(':')) {
+                bail!("mount '{spec}' has invalid options");
+            }
+            out.push(ProfileMount {
+                host: host.to_string(),
+                container: container.to_string(),
+                options: options.map(str::to_string),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Scope for a built-in cache kind (defaults to [`CacheScope::Env`]).
+    pub fn cache_scope(&self, kind: &str) -> CacheScope {
+        self.caches
+            .get(kind)
+            .map(|c| c.cache_scope)
+            .unwrap_or_default()
+    }
+}
+
+/// Expand `${env:NAME}` (error if unset) and `${env:NAME:-default}` (default if unset)
+/// in `value`. Any other text, including other `${...}` forms, is kept as is.
+pub fn interpolate_host_env(
+    value: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String> {
+    const OPEN: &str = "${env:";
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find(OPEN) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + OPEN.len()..];
+        let Some(end) = after.find('}') else {
+            bail!("unterminated '${{env:' in '{value}'");
+        };
+        let (name, default) = match after[..end].split_once(":-") {
+            Some((n, d)) => (n, Some(d)),
+            None => (&after[..end], None),
+        };
+        if !is_env_var_name(name) {
+            bail!("invalid host variable name '{name}' in '{value}'");
+        }
+        match (lookup(name), default) {
+            (Some(v), _) => out.push_str(&v),
+            (None, Some(d)) => out.push_str(d),
+            (None, None) => bail!(
+                "host environment variable '{name}' is not set (use '${{env:{name}:-default}}' for a fallback)"
+            ),
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn resolve_profile(
+    profiles: &BTreeMap<String, Profile>,
+    name: &str,
+    resolved: &mut BTreeMap<String, Profile>,
+    chain: &mut Vec<String>,
+) -> Result<()> {
+    if resolved.contains_key(name) {
+        return Ok(());
+    }
+    if let Some(start) = chain.iter().position(|c| c == name) {
+        let mut cycle = chain[start..].to_vec();
+        cycle.push(name.to_string());
+        bail!("profile extends cycle: {}", cycle.join(" -> "));
+    }
+    let mut profile = profiles[name].clone();
+    if let Some(parent) = &profile.extends {
+        if !profiles.contains_key(parent) {
+            bail!("profile '{name}' extends missing profile '{parent}'");
+        }
+        chain.push(name.to_string());
+        resolve_profile(profiles, parent, resolved, chain)?;
+        chain.pop();
+
+        let base = &resolved[parent];
+        if profile.container.is_empty() {
+            profile.container = base.container.clone();
+        }
+        profile.env_file_keys.extend(
+            base.env_file_keys
+                .iter()
+                .filter(|k| !profile.env.contains_key(*k))
+                .cloned(),
+        );
+        let mut env = base.env.clone();
+        env.extend(profile.env);
+        profile.env = env;
+    }
+    resolved.insert(name.to_string(), profile);
+    Ok(())
+}
+
+/// Env var name: ASCII letters, digits and `_`, not starting with a digit.
+pub fn is_env_var_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse `.env`-style text (profile `env_file`, `run --env-file`): `KEY=VALUE` lines, with
+/// blank lines and `#` comments ignored. The value is everything after the first `=`,
+/// verbatim (no quote stripping).
+pub fn parse_env_file(text: &str) -> Result<BTreeMap<String, String>> {
+    let mut env = BTreeMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((k, v)) = line.split_once('=') else {
+            bail!("line {}: expected KEY=VALUE", i + 1);
+        };
+        let k = k.trim_end();
+        if !is_env_var_name(k) {
+            // Never echo the line: it may hold a secret.
+            bail!("line {}: invalid env key", i + 1);
+        }
+        env.insert(k.to_string(), v.to_string());
+    }
+    Ok(env)
+}
+
+/// RFC 1123 label: 1-63 ASCII letters, digits and `-`, not starting or ending with `-`.
+fn is_dns_label(s: &str) -> bool {
+    (1..=63).contains(&s.len())
+        && !s.starts_with('-')
+        && !s.ends_with('-')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Explicit image refs contain `/`, `:` or `@`; anything else is a template name.
+fn is_explicit_image_ref(container: &str) -> bool {
+    container.contains(['/', ':', '@'])
+}
+
+/// Lowercase an explicit image ref up to its tag or digest (registries reject
+/// uppercase repository names; tags and digests keep their case). Template names are
+/// case-sensitive and returned unchanged.
+pub fn normalize_container_ref(container: &str) -> String {
+    if !is_explicit_image_ref(container) {
+        return container.to_string();
+    }
+    let (name_tag, digest) = match container.split_once('@') {
+        Some((n, d)) => (n, Some(d)),
+        None => (container, None),
+    };
+    let last_slash = name_tag.rfind('/').map_or(0, |i| i + 1);
+    let (name, tag) = match name_tag[last_slash..].rfind(':') {
+        Some(i) => name_tag.split_at(last_slash + i),
+        None => (name_tag, ""),
+    };
+    let mut out = name.to_ascii_lowercase();
+    out.push_str(tag);
+    if let Some(d) = digest {
+        out.push('@');
+        out.push_str(d);
+    }
+    out
+}
+
+/// What a `container` value names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerRefKind {
+    /// An embedded podCI template (`rust-debian`), built locally.
+    SymbolicTemplate,
+    /// An image reference used as is (`docker.io/library/ubuntu:24.04`).
+    ExplicitImageRef,
+}
+
+/// Classify a container ref, rejecting malformed refs (see [`check_container_ref`]) and
+/// bare names that are not templates.
+pub fn classify_container_ref(container: &str) -> Result<ContainerRefKind> {
+    check_container_ref(container)?;
+    if podci_templates::containerfile_for(container).is_ok() {
+        return Ok(ContainerRefKind::SymbolicTemplate);
+    }
+
+    // External images must be explicit to avoid ambiguity with symbolic template names.
+    // Accepted forms include:
+    //   - registry/namespace/name[:tag]
+    //   - name[:tag]
+    //   - name@sha256:<digest>
+    if is_explicit_image_ref(container) {
+        return Ok(ContainerRefKind::ExplicitImageRef);
+    }
+
+    bail!(
+        "unknown container template '{container}'. To use an external image, specify an explicit image reference (e.g. 'docker.io/library/ubuntu:24.04')."
+    );
+}
+
+/// Syntax check for a container ref: no surrounding whitespace, and explicit image refs
+/// use only common image-ref characters. [`classify_container_ref`] also checks that a
+/// bare name is a known template.
+pub fn check_container_ref(container: &str) -> Result<()> {
+    if container.trim() != container {
+        bail!("container '{container}' has leading or trailing whitespace");
+    }
+    // Not a full OCI reference parser; a guardrail to prevent surprises.
+    if is_explicit_image_ref(container)
+        && container
+            .chars()
+            .any(|c| !(c.is_ascii_alphanumeric() || ".-_/@:".contains(c)))
+    {
+        bail!(
+            "invalid container reference '{container}': use only ASCII alphanumerics and .-_/ @ : (no whitespace)"
+        );
+    }
+    Ok(())
+}
+
+/// Keep the combinations matching `filters` (`--matrix-filter key=value`): values for
+/// the same axis are alternatives, different axes must all match.
+pub fn filter_matrix(
+    combos: Vec<ResolvedJob>,
+    filters: &[(String, String)],
+) -> Result<Vec<ResolvedJob>> {
+    let mut wanted: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (k, v) in filters {
+        wanted.entry(k.as_str()).or_default().push(v.as_str());
+    }
+    if let Some(first) = combos.first() {
+        for axis in wanted.keys() {
+            if !first.axes.contains_key(*axis) {
+                let have: Vec<&str> = first.axes.keys().map(String::as_str).collect();
+                bail!(
+                    "unknown matrix axis '{axis}' for job '{}' (axes: {})",
+                    first.job,
+                    if have.is_empty() {
+                        "none".to_string()
+                    } else {
+                        have.join(", ")
+                    }
+                );
+            }
+        }
+    }
+    let out: Vec<ResolvedJob> = combos
+        .into_iter()
+        .filter(|c| {
+            wanted
+                .iter()
+                .all(|(axis, values)| values.contains(&c.axes[*axis].as_str()))
+        })
+        .collect();
+    if out.is_empty() {
+        bail!("no matrix combination matches the --matrix-filter values");
+    }
+    Ok(out)
+}
+
+fn validate_matrix(job_name: &str, job: &Job, profiles: &BTreeMap<String, Profile>) -> Result<()> {
+    for (axis, values) in &job.matrix {
+        if !is_env_var_name(axis) {
+            bail!("job '{job_name}' matrix axis '{axis}' must be a valid env var name");
+        }
+        if values.is_empty() {
+            bail!("job '{job_name}' matrix axis '{axis}' has no values");
+        }
+        let mut seen = BTreeSet::new();
+        for v in values {
+            if !seen.insert(v) {
+                bail!("job '{job_name}' matrix axis '{axis}' lists '{v}' twice");
+            }
+            if axis == MATRIX_PROFILE_AXIS && !profiles.contains_key(v) {
+                bail!("job '{job_name}' matrix references missing profile '{v}'");
+            }
+            if axis == MATRIX_CONTAINER_AXIS {
+                if let Err(e) = classify_container_ref(v) {
+                    bail!("job '{job_name}' matrix {e}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_step_order(job_name: &str, job: &Job) -> Result<()> {
+    if job.step_order.is_empty() {
+        if !job.steps.is_empty() {
+            bail!("job '{job_name}' has steps but empty step_order");
+        }
+        return Ok(());
+    }
+
+    let mut seen = BTreeSet::new();
+    for s in &job.step_order {
+        if !seen.insert(s.clone()) {
+            bail!("job '{job_name}' step_order contains duplicate step '{s}'");
+        }
+        if !job.steps.contains_key(s) {
+            bail!("job '{job_name}' step_order references missing step '{s}'");
+        }
+    }
+
+    // No steps outside step_order (prevents hidden drift)
+    let order: BTreeSet<_> = job.step_order.iter().cloned().collect();
+    let extras: Vec<_> = job
+        .steps
+        .keys()
+        .filter(|k| !order.contains(*k))
+        .cloned()
+        .collect();
+    if !extras.is_empty() {
+        bail!(
+            "job '{job_name}' has steps not listed in step_order: {:?}",
+            extras
+        );
+    }
+
+    // Basic sanity: each step must have a non-empty argv
+    for (step_name, step) in &job.steps {
+        if step.run.is_empty() {
+            bail!("job '{job_name}' step '{step_name}' has empty run argv");
+        }
+        if step.timeout_seconds == Some(0) {
+            bail!("job '{job_name}' step '{step_name}' timeout_seconds must be greater than 0");
+        }
+        if step.continue_on.contains(&0) {
+            bail!("job '{job_name}' step '{step_name}' continue_on must not contain 0 (success)");
+        }
+        if step.continue_on_error && !step.continue_on.is_empty() {
+            bail!("job '{job_name}' step '{step_name}' sets both continue_on and continue_on_error; use one");
+        }
+        if let Some(h) = &step.hostname {
+            if !is_dns_label(h) {
+                bail!(
+                    "job '{job_name}' step '{step_name}' hostname must be a DNS label (got '{h}')"
+                );
+            }
+        }
+    }
+
+    for (step_name, step) in &job.steps {
+        let mut seen = BTreeSet::new();
+        for d in &step.depends_on {
+            if !seen.insert(d) {
+                bail!(
+                    "job '{job_name}' step '{step_name}' depends_on contains duplicate step '{d}'"
+                );
+            }
+            if !job.steps.contains_key(d) {
+                bail!(
+                    "job '{job_name}' step '{step_name}' depends_on references missing step '{d}'"
+                );
+            }
+        }
+    }
+    job.execution_order()
+        .map_err(|e| anyhow!("job '{job_name}' has a {e}"))?;
+
+    Ok(())
+}
+
+/// `$id` of the `podci.toml` JSON Schema.
+pub const CONFIG_SCHEMA_ID: &str = "podci-config.v1";
+
+/// JSON Schema for `podci.toml` ([`Config`]), for editors that validate and complete
+/// TOML against a schema.
+pub fn config_json_schema() -> serde_json::Value {
+    let mut schema = serde_json::to_value(schemars::schema_for!(Config))
+        .expect("a derived schema always serializes");
+    schema["$id"] = CONFIG_SCHEMA_ID.into();
+    schema["title"] = "podci.toml".into();
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_wrong_version() {
+        let s = r#"
+version = 2
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+
+[jobs.default]
+profile = "dev"
+step_order = []
+steps = {}
+"#;
+        let err = Config::from_toml_str(s).unwrap_err();
+        assert!(err.to_string().contains("unsupported config version"));
+    }
+
+    #[test]
+    fn enforces_step_order_refs() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
 
 [jobs.default]
 profile = "dev"
@@ -193,4 +1381,1107 @@ run = ["echo", "hi"]
         assert_eq!(cfg.version, 1);
         assert!(cfg.jobs.contains_key("default"));
     }
+
+    #[test]
+    fn cache_scope_defaults_to_env_and_rejects_unknown_kinds() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+
+[profiles.dev.caches.target]
+cache_scope = "profile"
+
+[jobs.default]
+profile = "dev"
+step_order = []
+steps = {}
+"#;
+        let cfg = Config::from_toml_str(s).unwrap();
+        let dev = cfg.profile("dev").unwrap();
+        assert_eq!(dev.cache_scope("target"), CacheScope::Profile);
+        assert_eq!(dev.cache_scope("cargo_git"), CacheScope::Env);
+
+        let bad = s.replace("caches.target", "caches.ccache");
+        let err = Config::from_toml_str(&bad).unwrap_err();
+        assert!(err.to_string().contains("unknown cache 'ccache'"));
+    }
+
+    #[test]
+    fn step_timeout_precedence() {
+        let s = r#"
+version = 1
+project = "x"
+default_timeout_seconds = 600
+
+[profiles.dev]
+container = "rust-debian"
+
+[jobs.default]
+profile = "dev"
+step_order = ["a", "b"]
+
+[jobs.default.steps.a]
+run = ["echo", "a"]
+
+[jobs.default.steps.b]
+run = ["echo", "b"]
+timeout_seconds = 30
+"#;
+        let mut cfg = Config::from_toml_str(s).unwrap();
+        let steps = cfg.jobs["default"].steps.clone();
+        let dev = cfg.profiles["dev"].clone();
+        assert_eq!(cfg.effective_timeout_seconds(&dev, &steps["a"]), Some(600));
+        assert_eq!(cfg.effective_timeout_seconds(&dev, &steps["b"]), Some(30));
+
+        let mut dev_with_timeout = dev.clone();
+        dev_with_timeout.timeout_seconds = Some(120);
+        assert_eq!(
+            cfg.effective_timeout_seconds(&dev_with_timeout, &steps["a"]),
+            Some(120)
+        );
+
+        cfg.default_timeout_seconds = None;
+        assert_eq!(cfg.effective_timeout_seconds(&dev, &steps["a"]), None);
+    }
+
+    #[test]
+    fn images_tag_strategy_defaults_to_version() {
+        let base = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+
+[jobs.default]
+profile = "dev"
+step_order = ["fmt"]
+
+[jobs.default.steps.fmt]
+run = ["cargo", "fmt"]
+"#;
+        let cfg = Config::from_toml_str(base).unwrap();
+        assert_eq!(cfg.images.tag_strategy, TagStrategy::Version);
+
+        let with_images = format!("{base}\n[images]\ntag_strategy = \"content-hash\"\n");
+        let cfg = Config::from_toml_str(&with_images).unwrap();
+        assert_eq!(cfg.images.tag_strategy, TagStrategy::ContentHash);
+    }
+
+    #[test]
+    fn cli_overrides_precedence() {
+        let s = r#"
+version = 1
+project = "x"
+default_timeout_seconds = 600
+
+[profiles.dev]
+container = "rust-debian"
+
+[profiles.slow]
+container = "rust-debian"
+timeout_seconds = 3600
+
+[jobs.default]
+profile = "dev"
+step_order = ["a", "b"]
+
+[jobs.default.steps.a]
+run = ["echo", "a"]
+
+[jobs.default.steps.b]
+run = ["echo", "b"]
+timeout_seconds = 30
+"#;
+        let base = Config::from_toml_str(s).unwrap();
+        let timeouts = |cfg: &Config| {
+            let job = cfg.job("default").unwrap();
+            let p = cfg.profile(&job.profile).unwrap();
+            (
+                cfg.effective_timeout_seconds(p, &job.steps["a"]),
+                cfg.effective_timeout_seconds(p, &job.steps["b"]),
+            )
+        };
+
+        // No overrides: config wins.
+        let mut cfg = base.clone();
+        cfg.apply_overrides("default", &CliOverrides::default())
+            .unwrap();
+        assert_eq!(cfg.jobs["default"].profile, "dev");
+        assert_eq!(timeouts(&cfg), (Some(600), Some(30)));
+
+        // --default-timeout replaces the global default but not step timeouts.
+        let mut cfg = base.clone();
+        let o = CliOverrides {
+            default_timeout_seconds: Some(60),
+            ..Default::default()
+        };
+        cfg.apply_overrides("default", &o).unwrap();
+        assert_eq!(timeouts(&cfg), (Some(60), Some(30)));
+
+        // --profile swaps the job profile; the profile timeout beats the global default.
+        let mut cfg = base.clone();
+        let o = CliOverrides {
+            profile: Some("slow".to_string()),
+            default_timeout_seconds: Some(60),
+            ..Default::default()
+        };
+        cfg.apply_overrides("default", &o).unwrap();
+        assert_eq!(cfg.jobs["default"].profile, "slow");
+        assert_eq!(timeouts(&cfg), (Some(3600), Some(30)));
+
+        // --container applies to the effective (possibly overridden) profile only.
+        let mut cfg = base.clone();
+        let o = CliOverrides {
+            profile: Some("slow".to_string()),
+            container: Some("docker.io/library/rust:1".to_string()),
+            ..Default::default()
+        };
+        cfg.apply_overrides("default", &o).unwrap();
+        assert_eq!(cfg.profiles["slow"].container, "docker.io/library/rust:1");
+        assert_eq!(cfg.profiles["dev"].container, "rust-debian");
+
+        // Unknown job/profile are rejected.
+        let mut cfg = base.clone();
+        assert!(cfg
+            .apply_overrides("nope", &CliOverrides::default())
+            .is_err());
+        let o = CliOverrides {
+            profile: Some("nope".to_string()),
+            ..Default::default()
+        };
+        assert!(cfg.apply_overrides("default", &o).is_err());
+    }
+
+    #[test]
+    fn container_refs_are_normalized_and_checked_at_load() {
+        for (raw, want) in [
+            ("rust-debian", "rust-debian"),
+            ("Rust-Debian", "Rust-Debian"),
+            (
+                "Docker.io/Library/Ubuntu:24.04",
+                "docker.io/library/ubuntu:24.04",
+            ),
+            ("ghcr.io/Org/Img:Release-RC1", "ghcr.io/org/img:Release-RC1"),
+            ("localhost:5000/Img", "localhost:5000/img"),
+            ("Img@sha256:ABC", "img@sha256:ABC"),
+        ] {
+            assert_eq!(normalize_container_ref(raw), want, "{raw}");
+        }
+
+        let with_container = |c: &str| {
+            Config::from_toml_str(&format!(
+                r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "{c}"
+
+[jobs.default]
+profile = "dev"
+step_order = ["fmt"]
+
+[jobs.default.steps.fmt]
+run = ["cargo", "fmt"]
+"#
+            ))
+        };
+        let cfg = with_container("Docker.io/Library/Ubuntu:24.04").unwrap();
+        assert_eq!(
+            cfg.profiles["dev"].container,
+            "docker.io/library/ubuntu:24.04"
+        );
+        for bad in [
+            " rust-debian",
+            "rust-debian ",
+            "ubuntu:24.04\t",
+            "ubuntu:24.04!",
+        ] {
+            let err = with_container(bad).unwrap_err().to_string();
+            assert!(err.starts_with("profile 'dev'"), "{bad:?}: {err}");
+        }
+    }
+
+    #[test]
+    fn profile_mounts_reject_escapes_and_builtin_paths() {
+        let with_mount = |m: &str| {
+            Config::from_toml_str(&format!(
+                r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+mounts = ["{m}"]
+
+[jobs.default]
+profile = "dev"
+step_order = ["fmt"]
+
+[jobs.default.steps.fmt]
+run = ["cargo", "fmt"]
+"#
+            ))
+        };
+        let cfg = with_mount("/var/cache/ccache:/ccache:rw,z").unwrap();
+        assert_eq!(
+            cfg.profiles["dev"].parsed_mounts().unwrap(),
+            vec![ProfileMount {
+                host: "/var/cache/ccache".to_string(),
+                container: "/ccache".to_string(),
+                options: Some("rw,z".to_string()),
+            }]
+        );
+        assert!(with_mount(".ccache:/ccache").is_ok());
+        assert!(with_mount("/work-cache:/workspace").is_ok());
+        assert!(with_mount("/c:/usr/local/bin").is_ok());
+        for bad in [
+            "/ccache",
+            "../ccache:/ccache",
+            "a/../../b:/ccache",
+            "/c:ccache",
+            "/c:/x/../work",
+            "/c:/work",
+            "/c:/work/target/",
+            "/c:/work/src",
+            "/c:/usr/local/cargo",
+            "/c:/usr/local/cargo/registry",
+            "/c:/usr/local",
+            "/c:/usr/",
+            "/c:/ccache:",
+        ] {
+            let err = with_mount(bad).unwrap_err().to_string();
+            assert!(err.starts_with("profile 'dev' mount"), "{bad}: {err}");
+        }
+    }
+
+    #[test]
+    fn cargo_home_defaults_overrides_and_disables() {
+        let base = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+
+[jobs.default]
+profile = "dev"
+step_order = ["fmt"]
+
+[jobs.default.steps.fmt]
+run = ["cargo", "fmt"]
+"#;
+        let cfg = Config::from_toml_str(base).unwrap();
+        assert_eq!(cfg.profiles["dev"].cargo_home(), Some(DEFAULT_CARGO_HOME));
+
+        let custom = format!("{base}\n[profiles.dev.cargo]\nhome = \"/home/user/.cargo\"\n");
+        let cfg = Config::from_toml_str(&custom).unwrap();
+        assert_eq!(cfg.profiles["dev"].cargo_home(), Some("/home/user/.cargo"));
+
+        let off = format!("{base}\n[profiles.dev.cargo]\nenabled = false\n");
+        let cfg = Config::from_toml_str(&off).unwrap();
+        assert_eq!(cfg.profiles["dev"].cargo_home(), None);
+
+        let rel = format!("{base}\n[profiles.dev.cargo]\nhome = \"cargo\"\n");
+        assert!(Config::from_toml_str(&rel).is_err());
+    }
+
+    #[test]
+    fn warnings_cover_shell_steps_empty_env_and_unused_profiles() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+env = {}
+
+[profiles.spare]
+container = "rust-debian"
+
+[profiles.base]
+container = "rust-debian"
+
+[profiles.ci]
+extends = "base"
+
+[jobs.default]
+profile = "dev"
+step_order = ["fmt", "script"]
+matrix = { profile = ["dev", "ci"] }
+
+[jobs.default.steps.fmt]
+run = ["cargo", "fmt"]
+
+[jobs.default.steps.script]
+run = ["/bin/bash", "-c", "make all"]
+"#;
+        let (_cfg, warnings) =
+            Config::from_toml_str_with_warnings(s, std::path::Path::new(".")).unwrap();
+        let codes: Vec<&str> = warnings.iter().map(|w| w.code).collect();
+        assert_eq!(codes, vec!["shell-step", "empty-env", "unused-profile"]);
+        assert!(warnings[0].message.contains("'script'"));
+        assert!(warnings[2].to_string().starts_with("[unused-profile]"));
+        // `base` is only an `extends` parent, `ci` only a matrix axis value.
+        assert!(warnings[2].message.contains("'spare'"), "{}", warnings[2]);
+    }
+
+    #[test]
+    fn continue_on_lists_advisory_exit_codes() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+
+[jobs.default]
+profile = "dev"
+step_order = ["audit"]
+
+[jobs.default.steps.audit]
+run = ["cargo", "audit"]
+continue_on = [1]
+"#;
+        let cfg = Config::from_toml_str(s).unwrap();
+        let audit = &cfg.jobs["default"].steps["audit"];
+        assert!(audit.continues_on(1));
+        assert!(!audit.continues_on(2));
+        assert!(!audit.continues_on(0));
+
+        let zero = s.replace("continue_on = [1]", "timeout_seconds = 0");
+        let err = Config::from_toml_str(&zero).unwrap_err().to_string();
+        assert!(
+            err.contains("timeout_seconds must be greater than 0"),
+            "{err}"
+        );
+
+        let bad = s.replace("continue_on = [1]", "continue_on = [0, 1]");
+        let err = Config::from_toml_str(&bad).unwrap_err().to_string();
+        assert!(err.contains("must not contain 0"), "{err}");
+
+        let soft = s.replace("continue_on = [1]", "continue_on_error = true");
+        let cfg = Config::from_toml_str(&soft).unwrap();
+        assert!(cfg.jobs["default"].steps["audit"].continue_on_error);
+
+        let both = s.replace(
+            "continue_on = [1]",
+            "continue_on = [1]\ncontinue_on_error = true",
+        );
+        let err = Config::from_toml_str(&both).unwrap_err().to_string();
+        assert!(
+            err.contains("both continue_on and continue_on_error"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn skipped_step_must_still_be_in_step_order() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+
+[jobs.default]
+profile = "dev"
+step_order = ["fmt", "slow"]
+
+[jobs.default.steps.fmt]
+run = ["cargo", "fmt"]
+
+[jobs.default.steps.slow]
+run = ["cargo", "test"]
+skip = true
+"#;
+        let cfg = Config::from_toml_str(s).unwrap();
+        assert!(cfg.jobs["default"].steps["slow"].skip);
+        assert!(!cfg.jobs["default"].steps["fmt"].skip);
+
+        let missing = s.replace(r#"step_order = ["fmt", "slow"]"#, r#"step_order = ["fmt"]"#);
+        assert!(Config::from_toml_str(&missing).is_err());
+    }
+
+    #[test]
+    fn env_overrides_win_over_profile_and_step_env() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+env = { RUST_LOG = "info", CI = "1" }
+
+[jobs.default]
+profile = "dev"
+step_order = ["a", "b"]
+
+[jobs.default.steps.a]
+run = ["cargo", "test"]
+
+[jobs.default.steps.b]
+run = ["cargo", "test"]
+env = { RUST_LOG = "warn" }
+"#;
+        let mut cfg = Config::from_toml_str(s).unwrap();
+        let o = CliOverrides {
+            env: vec![("RUST_LOG".to_string(), "debug".to_string())],
+            ..Default::default()
+        };
+        cfg.apply_overrides("default", &o).unwrap();
+        let steps = &cfg.jobs["default"].steps;
+        for step in ["a", "b"] {
+            assert_eq!(steps[step].env["RUST_LOG"], "debug", "{step}");
+        }
+        assert_eq!(cfg.profiles["dev"].env["CI"], "1");
+        assert!(cfg.apply_overrides("nope", &o).is_err());
+    }
+
+    #[test]
+    fn step_workdir_overrides_profile_workdir() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+workdir = "crates/foo"
+
+[jobs.default]
+profile = "dev"
+step_order = ["a", "b"]
+
+[jobs.default.steps.a]
+run = ["cargo", "test"]
+
+[jobs.default.steps.b]
+run = ["cargo", "test"]
+workdir = "crates/bar"
+"#;
+        let cfg = Config::from_toml_str(s).unwrap();
+        let dev = &cfg.profiles["dev"];
+        let job = &cfg.jobs["default"];
+        assert_eq!(
+            job.effective_workdir(dev, &job.steps["a"]),
+            Some("crates/foo")
+        );
+        assert_eq!(
+            job.effective_workdir(dev, &job.steps["b"]),
+            Some("crates/bar")
+        );
+
+        for bad in ["/abs", "crates/../.."] {
+            let t = s.replace(r#"workdir = "crates/foo""#, &format!("workdir = {bad:?}"));
+            assert!(Config::from_toml_str(&t).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn job_workdir_sits_between_step_and_profile() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+workdir = "crates/foo"
+
+[jobs.default]
+profile = "dev"
+step_order = ["a", "b"]
+workdir = "crates/job"
+
+[jobs.default.steps.a]
+run = ["cargo", "test"]
+
+[jobs.default.steps.b]
+run = ["cargo", "test"]
+workdir = "crates/bar"
+"#;
+        let cfg = Config::from_toml_str(s).unwrap();
+        let dev = &cfg.profiles["dev"];
+        let job = &cfg.jobs["default"];
+        assert_eq!(
+            job.effective_workdir(dev, &job.steps["a"]),
+            Some("crates/job")
+        );
+        assert_eq!(
+            job.effective_workdir(dev, &job.steps["b"]),
+            Some("crates/bar")
+        );
+        assert_eq!(
+            cfg.planned_step("default", "a").unwrap().workdir.as_deref(),
+            Some("crates/job")
+        );
+
+        for bad in ["/abs", "crates/../.."] {
+            let t = s.replace(r#"workdir = "crates/job""#, &format!("workdir = {bad:?}"));
+            let err = Config::from_toml_str(&t).unwrap_err().to_string();
+            assert!(err.contains("job 'default' workdir"), "{bad}: {err}");
+        }
+    }
+
+    #[test]
+    fn env_key_inventory_lists_every_definition() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+env = { RUST_LOG = "info", CI = "1" }
+
+[jobs.default]
+profile = "dev"
+step_order = ["test"]
+
+[jobs.default.steps.test]
+run = ["cargo", "test"]
+env = { RUST_LOG = "debug" }
+"#;
+        let cfg = Config::from_toml_str(s).unwrap();
+        let inv = cfg.env_key_inventory();
+        assert_eq!(inv.keys().collect::<Vec<_>>(), ["CI", "RUST_LOG"]);
+
+        let defs = &inv["RUST_LOG"];
+        assert_eq!(defs.len(), 2);
+        assert_eq!(defs[0].location.to_string(), "profiles.dev.env");
+        assert_eq!(defs[0].value, "info");
+        assert_eq!(defs[1].location.to_string(), "jobs.default.steps.test.env");
+        assert_eq!(defs[1].value, "debug");
+    }
+
+    #[test]
+    fn plan_steps_resolves_order_profile_and_skips() {
+        let s = r#"
+version = 1
+project = "x"
+default_timeout_seconds = 60
+
+[profiles.dev]
+container = "rust-debian"
+workdir = "crates"
+
+[jobs.default]
+profile = "dev"
+step_order = ["test", "fmt", "build"]
+
+[jobs.default.steps.test]
+run = ["cargo", "test"]
+depends_on = ["build"]
+
+[jobs.default.steps.fmt]
+run = ["cargo", "fmt"]
+skip = true
+
+[jobs.default.steps.build]
+run = ["cargo", "build"]
+workdir = "."
+timeout_seconds = 5
+"#;
+        let cfg = Config::from_toml_str(s).unwrap();
+        let plan = cfg.plan_steps("default", None).unwrap();
+        let names: Vec<&str> = plan.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["fmt", "build", "test"]);
+        assert_eq!(plan[0].skip_reason.as_deref(), Some("skip = true"));
+        assert_eq!(
+            plan[1],
+            PlannedStep {
+                name: "build".into(),
+                run: vec!["cargo".into(), "build".into()],
+                profile: "dev".into(),
+                container: "rust-debian".into(),
+                workdir: Some(".".into()),
+                timeout_seconds: Some(5),
+                skip_reason: None,
+            }
+        );
+        assert_eq!(plan[2].workdir.as_deref(), Some("crates"));
+        assert_eq!(plan[2].timeout_seconds, Some(60));
+
+        // A selected step runs alone, without its dependencies.
+        let only = cfg.plan_steps("default", Some("test")).unwrap();
+        assert_eq!(only.len(), 1);
+        let err = cfg.plan_steps("default", Some("nope")).unwrap_err();
+        assert!(err.to_string().contains("unknown step 'nope'"), "{err}");
+    }
+
+    #[test]
+    fn depends_on_reorders_steps_with_step_order_as_tiebreaker() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+
+[jobs.default]
+profile = "dev"
+step_order = ["test", "fmt", "build", "lint"]
+
+[jobs.default.steps.test]
+run = ["cargo", "test"]
+depends_on = ["build"]
+
+[jobs.default.steps.fmt]
+run = ["cargo", "fmt", "--check"]
+
+[jobs.default.steps.build]
+run = ["cargo", "build"]
+
+[jobs.default.steps.lint]
+run = ["cargo", "clippy"]
+depends_on = ["build"]
+"#;
+        let cfg = Config::from_toml_str(s).unwrap();
+        assert_eq!(
+            cfg.jobs["default"].execution_order().unwrap(),
+            vec!["fmt", "build", "test", "lint"]
+        );
+
+        let missing = s.replace(
+            r#"depends_on = ["build"]
+"#,
+            r#"depends_on = ["nope"]
+"#,
+        );
+        let err = Config::from_toml_str(&missing).unwrap_err().to_string();
+        assert!(
+            err.contains("depends_on references missing step 'nope'"),
+            "{err}"
+        );
+
+        let cycle = s.replace(
+            r#"run = ["cargo", "build"]"#,
+            r#"run = ["cargo", "build"]
+depends_on = ["lint"]"#,
+        );
+        let err = Config::from_toml_str(&cycle).unwrap_err().to_string();
+        assert!(
+            err.contains("job 'default' has a depends_on cycle: build -> lint -> build"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn profile_extends_inherits_container_and_merges_env() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.base]
+container = "rust-debian"
+env = { RUST_LOG = "info", CI = "1" }
+
+[profiles.dev]
+extends = "base"
+env = { RUST_LOG = "debug" }
+
+[profiles.alpine]
+extends = "dev"
+container = "rust-alpine"
+
+[jobs.default]
+profile = "alpine"
+step_order = ["a"]
+
+[jobs.default.steps.a]
+run = ["cargo", "test"]
+"#;
+        let cfg = Config::from_toml_str(s).unwrap();
+        let dev = &cfg.profiles["dev"];
+        assert_eq!(dev.container, "rust-debian");
+        assert_eq!(dev.env["RUST_LOG"], "debug");
+        assert_eq!(dev.env["CI"], "1");
+        let alpine = &cfg.profiles["alpine"];
+        assert_eq!(alpine.container, "rust-alpine");
+        assert_eq!(alpine.env, dev.env);
+
+        let missing = s.replace(r#"extends = "base""#, r#"extends = "nope""#);
+        let err = Config::from_toml_str(&missing).unwrap_err().to_string();
+        assert!(
+            err.contains("profile 'dev' extends missing profile 'nope'"),
+            "{err}"
+        );
+
+        let cycle = s.replace(
+            r#"container = "rust-debian"
+env"#,
+            r#"extends = "alpine"
+env"#,
+        );
+        let err = Config::from_toml_str(&cycle).unwrap_err().to_string();
+        assert!(
+            err.contains("profile extends cycle: alpine -> dev -> base -> alpine"),
+            "{err}"
+        );
+
+        let orphan = s.replace(r#"extends = "base""#, "");
+        let err = Config::from_toml_str(&orphan).unwrap_err().to_string();
+        assert!(err.contains("profile 'dev' has no container"), "{err}");
+    }
+
+    #[test]
+    fn env_file_merges_under_inline_env_and_is_inherited() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.base]
+container = "rust-debian"
+env_file = "ci.env"
+env = { RUST_LOG = "info" }
+
+[profiles.dev]
+extends = "base"
+env = { CI = "dev" }
+
+[jobs.default]
+profile = "dev"
+step_order = ["a"]
+
+[jobs.default.steps.a]
+run = ["cargo", "test"]
+"#;
+        let dir = std::env::temp_dir().join(format!("podci-env-file-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("ci.env"), "RUST_LOG=trace\nCI=1\nTZ=UTC\n").unwrap();
+
+        let cfg = Config::from_toml_str_in(s, &dir).unwrap();
+        let env = |p: &str| {
+            let e = &cfg.profiles[p].env;
+            e.iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(env("base"), ["CI=1", "RUST_LOG=info", "TZ=UTC"]);
+        assert_eq!(env("dev"), ["CI=dev", "RUST_LOG=info", "TZ=UTC"]);
+        // Only values that still come from the file are tracked as env_file values.
+        let keys = |p: &str| {
+            cfg.profiles[p]
+                .env_file_keys
+                .iter()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys("base"), ["CI", "TZ"]);
+        assert_eq!(keys("dev"), ["TZ"]);
+        let audit = cfg.env_key_inventory();
+        assert_eq!(
+            audit["TZ"][0].location.to_string(),
+            "profiles.base.env_file"
+        );
+        assert_eq!(
+            audit["RUST_LOG"][0].location.to_string(),
+            "profiles.base.env"
+        );
+
+        // Verbatim: `${env:...}` in the file is not expanded, unlike inline env.
+        std::fs::write(dir.join("ci.env"), "TZ=${env:TZ}\n").unwrap();
+        let mut cfg = Config::from_toml_str_in(s, &dir).unwrap();
+        cfg.expand_host_env("default", &|_| None).unwrap();
+        assert_eq!(cfg.profiles["dev"].env["TZ"], "${env:TZ}");
+
+        let missing = Config::from_toml_str_in(&s.replace("ci.env", "nope.env"), &dir)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            missing.contains("profile 'base' env_file: read"),
+            "{missing}"
+        );
+        for bad in ["/etc/ci.env", "../ci.env"] {
+            let err = Config::from_toml_str_in(&s.replace("ci.env", bad), &dir)
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains("env_file must be relative"), "{bad}: {err}");
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn workdir_warnings_flag_missing_dirs_only() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+workdir = "crates/foo"
+
+[jobs.default]
+profile = "dev"
+step_order = ["a", "b"]
+
+[jobs.default.steps.a]
+run = ["cargo", "test"]
+
+[jobs.default.steps.b]
+run = ["cargo", "test"]
+workdir = "crates/typo"
+"#;
+        let cfg = Config::from_toml_str(s).unwrap();
+        let root = std::env::temp_dir().join(format!("podci-workdirs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("crates/foo")).unwrap();
+
+        let w = cfg.workdir_warnings(&root);
+        assert_eq!(w.len(), 1, "{w:?}");
+        assert_eq!(w[0].code, "missing-workdir");
+        assert!(
+            w[0].message.contains("step 'b' workdir 'crates/typo'"),
+            "{}",
+            w[0].message
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn host_env_interpolation() {
+        let lookup = |k: &str| (k == "RUSTFLAGS").then(|| "-Dwarnings".to_string());
+        assert_eq!(
+            interpolate_host_env("${env:RUSTFLAGS} -Cdebuginfo=0", &lookup).unwrap(),
+            "-Dwarnings -Cdebuginfo=0"
+        );
+        assert_eq!(
+            interpolate_host_env("${env:MISSING:-fallback}", &lookup).unwrap(),
+            "fallback"
+        );
+        assert_eq!(
+            interpolate_host_env("${env:MISSING:-}", &lookup).unwrap(),
+            ""
+        );
+        assert_eq!(
+            interpolate_host_env("$HOME ${other} plain", &lookup).unwrap(),
+            "$HOME ${other} plain"
+        );
+
+        let err = interpolate_host_env("${env:MISSING}", &lookup).unwrap_err();
+        assert!(err.to_string().contains("'MISSING' is not set"), "{err}");
+        assert!(interpolate_host_env("${env:RUSTFLAGS", &lookup).is_err());
+        assert!(interpolate_host_env("${env:1BAD}", &lookup).is_err());
+
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+env = { RUSTFLAGS = "${env:RUSTFLAGS}" }
+
+[jobs.default]
+profile = "dev"
+step_order = ["a"]
+
+[jobs.default.steps.a]
+run = ["cargo", "test"]
+env = { LEVEL = "${env:LEVEL:-info}" }
+"#;
+        let mut cfg = Config::from_toml_str(s).unwrap();
+        cfg.expand_host_env("default", &lookup).unwrap();
+        assert_eq!(cfg.profiles["dev"].env["RUSTFLAGS"], "-Dwarnings");
+        assert_eq!(cfg.jobs["default"].steps["a"].env["LEVEL"], "info");
+
+        let mut cfg = Config::from_toml_str(s).unwrap();
+        let err = cfg.expand_host_env("default", &|_| None).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("profiles.dev.env.RUSTFLAGS"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn matrix_expands_every_combination() {
+        let s = r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "rust-debian"
+env = { TOOLCHAIN = "none" }
+
+[profiles.musl]
+container = "rust-alpine"
+
+[jobs.default]
+profile = "dev"
+step_order = ["a"]
+
+[jobs.default.matrix]
+container = ["rust-debian", "rust-alpine"]
+TOOLCHAIN = ["stable", "nightly"]
+
+[jobs.default.steps.a]
+run = ["cargo", "test"]
+"#;
+        let cfg = Config::from_toml_str(s).unwrap();
+        let combos = cfg.expand_matrix("default").unwrap();
+        let names: Vec<String> = combos.iter().map(ResolvedJob::display_name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "default[TOOLCHAIN=stable,container=rust-debian]",
+                "default[TOOLCHAIN=stable,container=rust-alpine]",
+                "default[TOOLCHAIN=nightly,container=rust-debian]",
+                "default[TOOLCHAIN=nightly,container=rust-alpine]",
+            ]
+        );
+
+        let mut applied = cfg.clone();
+        applied.apply_matrix("default", &combos[1].axes).unwrap();
+        assert_eq!(applied.profiles["dev"].container, "rust-alpine");
+        assert_eq!(applied.profiles["dev"].env["TOOLCHAIN"], "stable");
+
+        let profiles = s.replace(
+            r#"container = ["rust-debian", "rust-alpine"]"#,
+            r#"profile = ["dev", "musl"]"#,
+        );
+        let cfg = Config::from_toml_str(&profiles).unwrap();
+        let mut applied = cfg.clone();
+        let combos = cfg.expand_matrix("default").unwrap();
+        applied.apply_matrix("default", &combos[1].axes).unwrap();
+        assert_eq!(applied.jobs["default"].profile, "musl");
+
+        let filtered = filter_matrix(
+            cfg.expand_matrix("default").unwrap(),
+            &[
+                ("profile".to_string(), "musl".to_string()),
+                ("TOOLCHAIN".to_string(), "stable".to_string()),
+                ("TOOLCHAIN".to_string(), "nightly".to_string()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(filtered.len(), 2);
+        assert!(filtered.iter().all(|c| c.axes["profile"] == "musl"));
+        let unknown = filter_matrix(
+            cfg.expand_matrix("default").unwrap(),
+            &[("arch".to_string(), "x".to_string())],
+        );
+        assert!(unknown.is_err());
+
+        let mut adhoc = cfg.clone();
+        adhoc
+            .extend_matrix(
+                "default",
+                &[(
+                    "RUSTFLAGS".to_string(),
+                    vec![String::new(), "-C opt-level=2".to_string()],
+                )],
+            )
+            .unwrap();
+        assert_eq!(adhoc.expand_matrix("default").unwrap().len(), 8);
+        assert!(adhoc
+            .extend_matrix(
+                "default",
+                &[("profile".to_string(), vec!["nope".to_string()])]
+            )
+            .is_err());
+
+        let plain = s.replace(
+            r#"[jobs.default.matrix]
+container = ["rust-debian", "rust-alpine"]
+TOOLCHAIN = ["stable", "nightly"]
+"#,
+            "",
+        );
+        let combos = Config::from_toml_str(&plain)
+            .unwrap()
+            .expand_matrix("default")
+            .unwrap();
+        assert_eq!(combos.len(), 1);
+        assert_eq!(combos[0].display_name(), "default");
+        let bad = profiles.replace(r#""dev", "musl""#, r#""dev", "nope""#);
+        let err = Config::from_toml_str(&bad).unwrap_err().to_string();
+        assert!(
+            err.contains("matrix references missing profile 'nope'"),
+            "{err}"
+        );
+        let empty = s.replace(r#"["stable", "nightly"]"#, "[]");
+        assert!(Config::from_toml_str(&empty).is_err());
+    }
+
+    #[test]
+    fn config_json_schema_describes_profiles_and_jobs() {
+        let schema = config_json_schema();
+        assert_eq!(schema["$id"], CONFIG_SCHEMA_ID);
+        assert_eq!(schema["title"], "podci.toml");
+        for key in ["version", "project", "profiles", "jobs"] {
+            assert!(schema["properties"].get(key).is_some(), "{key}");
+        }
+        // Step and profile definitions are reachable, so nested tables validate too.
+        assert!(schema["definitions"].get("Profile").is_some());
+        assert!(schema["definitions"].get("Job").is_some());
+    }
+
+    #[test]
+    fn container_ref_classification_prefers_symbolic_templates() {
+        assert_eq!(
+            classify_container_ref("rust-debian").unwrap(),
+            ContainerRefKind::SymbolicTemplate
+        );
+    }
+
+    #[test]
+    fn container_ref_classification_allows_explicit_image_refs() {
+        assert_eq!(
+            classify_container_ref("docker.io/library/ubuntu:24.04").unwrap(),
+            ContainerRefKind::ExplicitImageRef
+        );
+        assert_eq!(
+            classify_container_ref("ubuntu:24.04").unwrap(),
+            ContainerRefKind::ExplicitImageRef
+        );
+        assert_eq!(
+            classify_container_ref("ghcr.io/org/img@sha256:deadbeef").unwrap(),
+            ContainerRefKind::ExplicitImageRef
+        );
+    }
+
+    #[test]
+    fn container_ref_classification_rejects_ambiguous_names() {
+        let err = classify_container_ref("ubuntu").unwrap_err().to_string();
+        assert!(err.contains("unknown container template"));
+        assert!(err.contains("explicit image reference"));
+    }
+
+    #[test]
+    fn validate_rejects_unknown_bare_container_names() {
+        let toml = |container: &str| {
+            format!(
+                r#"
+version = 1
+project = "x"
+
+[profiles.dev]
+container = "{container}"
+
+[jobs.default]
+profile = "dev"
+step_order = ["a"]
+
+[jobs.default.steps.a]
+run = ["true"]
+"#
+            )
+        };
+        for ok in [
+            "rust-debian",
+            "cpp-debian",
+            "docker.io/library/ubuntu:24.04",
+        ] {
+            Config::from_toml_str(&toml(ok)).unwrap();
+        }
+        let err = Config::from_toml_str(&toml("rust-debain")).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("unknown container template 'rust-debain'"),
+            "{err:#}"
+        );
+        assert!(Config::from_toml_str(&toml("ubuntu 24.04")).is_err());
+    }
 }
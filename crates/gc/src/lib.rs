@@ -9,14 +9,27 @@ use serde::{Deserialize, Serialize};
 pub struct Resource {
     pub name: String,
     pub created: DateTime<Utc>,
+    /// Disk usage; only consulted by [`PrunePolicy::max_total_bytes`].
+    #[serde(default)]
+    pub size_bytes: u64,
 }
 
 #[derive(Debug, Clone)]
 pub struct PrunePolicy {
     pub keep: usize,
     pub older_than_days: Option<i64>,
+    /// Size budget: besides anything past its age, the oldest resources are pruned
+    /// until the rest total at most this many bytes. Without `older_than_days`, only
+    /// the budget prunes. The newest `keep` are never pruned, so they alone can
+    /// exceed the budget.
+    pub max_total_bytes: Option<u64>,
+    /// Only resources created at or before this instant may be pruned (inclusive).
+    pub before: Option<DateTime<Utc>>,
+    /// Only resources created strictly after this instant may be pruned (exclusive).
+    pub after: Option<DateTime<Utc>>,
 }
 
+#[allow(clippy::unnecessary_sort_by)]
 pub fn select_prune_candidates(
     mut resources: Vec<Resource>,
     policy: &PrunePolicy,
@@ -28,21 +41,114 @@ pub fn select_prune_candidates(
         .map(|d| Utc::now() - Duration::days(d));
 
     let mut candidates = Vec::new();
+    // Retained resources beyond `keep`, newest first; the size budget evicts from the back.
+    let mut evictable = Vec::new();
+    let mut retained_bytes: u64 = 0;
     for (idx, r) in resources.into_iter().enumerate() {
         if idx < policy.keep {
+            retained_bytes = retained_bytes.saturating_add(r.size_bytes);
             continue;
         }
-        if let Some(cut) = cutoff {
-            if r.created >= cut {
-                continue;
-            }
+        // Outside the before/after window nothing is pruned, not even for size.
+        let in_window = policy.before.is_none_or(|b| r.created <= b)
+            && policy.after.is_none_or(|a| r.created > a);
+        if !in_window {
+            retained_bytes = retained_bytes.saturating_add(r.size_bytes);
+            continue;
+        }
+        // Age and size are alternatives: with neither, everything past `keep` goes.
+        let expired = match cutoff {
+            Some(cut) => r.created < cut,
+            None => policy.max_total_bytes.is_none(),
+        };
+        if expired {
+            candidates.push(r);
+        } else {
+            retained_bytes = retained_bytes.saturating_add(r.size_bytes);
+            evictable.push(r);
+        }
+    }
+
+    if let Some(budget) = policy.max_total_bytes {
+        while retained_bytes > budget {
+            let Some(r) = evictable.pop() else {
+                break;
+            };
+            retained_bytes -= r.size_bytes;
+            candidates.push(r);
         }
-        candidates.push(r);
     }
 
     Ok(candidates)
 }
 
+/// A podCI-built image, identified by `name` (id or tag). `version` is the podCI
+/// release that built it; `None` for images built before podCI recorded one.
+#[derive(Debug, Clone)]
+pub struct VersionedImage {
+    pub name: String,
+    pub version: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+/// Images of one podCI version selected for pruning.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ImageGroup {
+    /// The podCI version, or `unknown` for unlabeled images.
+    pub version: String,
+    pub images: Vec<String>,
+}
+
+/// Label value grouping images that carry no podCI version.
+pub const UNKNOWN_VERSION: &str = "unknown";
+
+/// Group images by podCI version and apply `policy` to the versions other than
+/// `current`, dated by their newest image: the newest `keep` of them survive.
+/// Images of `current` are never selected. Groups come back oldest version first.
+pub fn select_stale_image_groups(
+    images: Vec<VersionedImage>,
+    current: &str,
+    policy: &PrunePolicy,
+) -> Result<Vec<ImageGroup>> {
+    use std::collections::BTreeMap;
+
+    let mut by_version: BTreeMap<String, (DateTime<Utc>, Vec<String>)> = BTreeMap::new();
+    for i in images {
+        let version = i.version.unwrap_or_else(|| UNKNOWN_VERSION.to_string());
+        if version == current {
+            continue;
+        }
+        let e = by_version
+            .entry(version)
+            .or_insert_with(|| (i.created, Vec::new()));
+        e.0 = e.0.max(i.created);
+        e.1.push(i.name);
+    }
+
+    let versions = by_version
+        .iter()
+        .map(|(v, (created, _))| Resource {
+            name: v.clone(),
+            created: *created,
+            size_bytes: 0,
+        })
+        .collect();
+    let mut candidates = select_prune_candidates(versions, policy)?;
+    candidates.reverse();
+
+    Ok(candidates
+        .into_iter()
+        .filter_map(|c| {
+            let (_, mut images) = by_version.remove(&c.name)?;
+            images.sort();
+            Some(ImageGroup {
+                version: c.name,
+                images,
+            })
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -54,13 +160,162 @@ mod tests {
             .map(|i| Resource {
                 name: format!("r{i}"),
                 created: now - Duration::days(i),
+                size_bytes: 0,
             })
             .collect::<Vec<_>>();
         let pol = PrunePolicy {
             keep: 2,
             older_than_days: None,
+            max_total_bytes: None,
+            before: None,
+            after: None,
         };
         let c = select_prune_candidates(res, &pol).unwrap();
         assert_eq!(c.len(), 3);
     }
+
+    fn sized(now: DateTime<Utc>) -> Vec<Resource> {
+        // r0 newest .. r4 oldest, 10 bytes each except r3 (50).
+        (0..5)
+            .map(|i| Resource {
+                name: format!("r{i}"),
+                created: now - Duration::days(i),
+                size_bytes: if i == 3 { 50 } else { 10 },
+            })
+            .collect()
+    }
+
+    fn names(c: Vec<Resource>) -> Vec<String> {
+        c.into_iter().map(|r| r.name).collect()
+    }
+
+    #[test]
+    fn size_budget_evicts_oldest_first() {
+        let now = Utc::now();
+        let pol = PrunePolicy {
+            keep: 1,
+            older_than_days: None,
+            max_total_bytes: Some(40),
+            before: None,
+            after: None,
+        };
+        // Total 90: dropping r4 leaves 80, r3 leaves 30.
+        let c = select_prune_candidates(sized(now), &pol).unwrap();
+        assert_eq!(names(c), ["r4", "r3"]);
+
+        let pol = PrunePolicy {
+            max_total_bytes: Some(1_000),
+            ..pol
+        };
+        assert!(select_prune_candidates(sized(now), &pol)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn size_budget_never_evicts_kept_and_adds_to_age() {
+        let now = Utc::now();
+        // r0..r2 are kept (30 bytes) even though the budget is smaller.
+        let pol = PrunePolicy {
+            keep: 3,
+            older_than_days: None,
+            max_total_bytes: Some(5),
+            before: None,
+            after: None,
+        };
+        let c = select_prune_candidates(sized(now), &pol).unwrap();
+        assert_eq!(names(c), ["r4", "r3"]);
+
+        // Age prunes r4 (4.5 days old); the budget then takes r3, r2 and r1.
+        let pol = PrunePolicy {
+            keep: 1,
+            older_than_days: Some(4),
+            max_total_bytes: Some(15),
+            before: None,
+            after: None,
+        };
+        let c = select_prune_candidates(sized(now - Duration::hours(12)), &pol).unwrap();
+        assert_eq!(names(c), ["r4", "r3", "r2", "r1"]);
+    }
+
+    #[test]
+    fn date_window_is_inclusive_before_and_exclusive_after() {
+        let now = Utc::now();
+        let day = |i: i64| now - Duration::days(i);
+        let res: Vec<Resource> = (0..5)
+            .map(|i| Resource {
+                name: format!("r{i}"),
+                created: day(i),
+                size_bytes: 0,
+            })
+            .collect();
+        // r1 sits exactly on `before` (included), r3 exactly on `after` (excluded).
+        let pol = PrunePolicy {
+            keep: 0,
+            older_than_days: None,
+            max_total_bytes: None,
+            before: Some(day(1)),
+            after: Some(day(3)),
+        };
+        let c = select_prune_candidates(res.clone(), &pol).unwrap();
+        assert_eq!(names(c), ["r1", "r2"]);
+
+        // keep still protects the newest N, even inside the window.
+        let pol = PrunePolicy {
+            keep: 2,
+            before: None,
+            ..pol
+        };
+        let c = select_prune_candidates(res, &pol).unwrap();
+        assert_eq!(names(c), ["r2"]);
+    }
+
+    #[test]
+    fn image_groups_spare_current_version_and_keep_newest_others() {
+        let now = Utc::now();
+        let img = |name: &str, version: Option<&str>, days: i64| VersionedImage {
+            name: name.to_string(),
+            version: version.map(String::from),
+            created: now - Duration::days(days),
+        };
+        let images = vec![
+            img("cur", Some("0.4.0"), 40),
+            img("v3-debian", Some("0.3.0"), 10),
+            img("v3-alpine", Some("0.3.0"), 12),
+            img("v2", Some("0.2.0"), 20),
+            img("old", None, 30),
+        ];
+        let pol = PrunePolicy {
+            keep: 1,
+            older_than_days: None,
+            max_total_bytes: None,
+            before: None,
+            after: None,
+        };
+        let groups = select_stale_image_groups(images.clone(), "0.4.0", &pol).unwrap();
+        assert_eq!(
+            groups,
+            vec![
+                ImageGroup {
+                    version: UNKNOWN_VERSION.to_string(),
+                    images: vec!["old".to_string()],
+                },
+                ImageGroup {
+                    version: "0.2.0".to_string(),
+                    images: vec!["v2".to_string()],
+                },
+            ]
+        );
+
+        let pol = PrunePolicy {
+            keep: 0,
+            older_than_days: Some(11),
+            max_total_bytes: None,
+            before: None,
+            after: None,
+        };
+        let groups = select_stale_image_groups(images, "0.4.0", &pol).unwrap();
+        let versions: Vec<&str> = groups.iter().map(|g| g.version.as_str()).collect();
+        assert_eq!(versions, [UNKNOWN_VERSION, "0.2.0"]);
+    }
 }
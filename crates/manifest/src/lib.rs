@@ -5,15 +5,21 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use etcetera::{choose_base_strategy, BaseStrategy};
 use rand::{distributions::Alphanumeric, Rng};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ManifestV1 {
     pub schema: String,
     pub podci_version: String,
     pub timestamp_utc: String,
+    /// Id of the run that wrote this manifest (its `runs/<run_id>/` directory).
+    /// Absent in manifests written before this field existed.
+    #[serde(default)]
+    pub run_id: Option<String>,
     pub project: String,
     pub job: String,
     pub profile: String,
@@ -31,32 +37,129 @@ pub struct ManifestV1 {
     /// values as "unknown".
     #[serde(default)]
     pub base_image_digest_status: Option<String>,
+    /// Tag strategy used for a locally built template image
+    /// ("version", "content-hash", "fixed"); absent for explicit image refs.
+    #[serde(default)]
+    pub image_tag_strategy: Option<String>,
+    /// Free-form `--label key=value` metadata for correlating runs with external systems.
+    /// Not part of `env_id`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// Matrix combination (`[jobs.<name>.matrix]` axis -> value) this run executed;
+    /// empty for jobs without a matrix.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub matrix: BTreeMap<String, String>,
+    /// `HEAD` commit of the repo root when the run started; absent outside a git repo,
+    /// without `git`, or before the first commit.
+    #[serde(default)]
+    pub git_commit: Option<String>,
+    /// Checked-out branch; absent on a detached `HEAD` (common in CI) or outside a git repo.
+    #[serde(default)]
+    pub git_branch: Option<String>,
+    /// True when the work tree had uncommitted changes (untracked files included) when
+    /// the run started; absent when git state could not be read.
+    #[serde(default)]
+    pub git_dirty: Option<bool>,
+    /// Wall-clock time of the step loop, from before the first step to after the last.
+    /// Absent in manifests written before this field existed.
+    #[serde(default)]
+    pub total_duration_ms: Option<u64>,
+    /// Podman and host the run executed on, for comparing runs across machines.
+    /// Absent in manifests written before this field existed.
+    #[serde(default)]
+    pub host_info: Option<ManifestHostInfoV1>,
     pub steps: Vec<ManifestStepV1>,
     pub result: ManifestResultV1,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ManifestStepV1 {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     pub argv: Vec<String>,
+    /// Merged (profile + step) env passed to the step container.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
     pub duration_ms: Option<u64>,
     pub exit_code: Option<i32>,
+    /// Times the step was executed (more than 1 with `retries`; 0 if it never ran).
+    #[serde(default)]
+    pub attempts: u32,
+    /// Effective timeout the step ran with (step > profile > default); absent when none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+    /// True when the step ran attached to a terminal (`run --attach`); its output was
+    /// shown live and not captured, so the log paths are absent.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub attached: bool,
+    /// True when the step is disabled in config (`skip = true`); it was not executed,
+    /// so duration, exit code, and log paths are absent.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    /// True when the step failed but has `continue_on_error = true`, so the run went on
+    /// and its result is unaffected. `exit_code` still records the failure.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub soft_failed: bool,
     /// Relative path (from the per-run directory) to the captured stdout log for this step.
     pub stdout_path: Option<String>,
     /// Relative path (from the per-run directory) to the captured stderr log for this step.
     pub stderr_path: Option<String>,
+    /// Captured stdout (redacted, as in the log file) with `run --embed-logs`; beyond the
+    /// cap only the tail is kept, after a truncation marker.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdout_inline: Option<String>,
+    /// Captured stderr with `run --embed-logs` (see `stdout_inline`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr_inline: Option<String>,
+}
+
+/// Best-effort podman/host facts; a field is absent when podman could not report it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ManifestHostInfoV1 {
+    /// Podman client version (e.g. `5.2.0`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub podman_version: Option<String>,
+    /// `host.os` from `podman info` (e.g. `linux`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+    /// `host.arch` from `podman info` (e.g. `amd64`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+    /// `host.security.rootless` from `podman info`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rootless: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ManifestResultV1 {
     pub ok: bool,
     pub exit_code: i32,
     pub error: Option<String>,
+    /// True when the run was stopped by SIGINT/SIGTERM (Ctrl-C or `podci cancel`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+}
+
+/// Environment variable that pins the run id (tests/automation only).
+pub const RUN_ID_ENV: &str = "PODCI_RUN_ID";
+
+/// Timestamp embedded in a generated run id (`YYYYMMDDTHHMMSSZ-...`), if present.
+pub fn run_id_timestamp(run_id: &str) -> Option<DateTime<Utc>> {
+    let ts = run_id.get(..16)?;
+    chrono::NaiveDateTime::parse_from_str(ts, "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|n| n.and_utc())
 }
 
 pub fn new_run_id() -> String {
-    let ts = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
-    let rand: String = rand::thread_rng()
+    new_run_id_with(Utc::now(), &mut rand::thread_rng())
+}
+
+/// Seedable variant of [`new_run_id`]: same timestamp + same RNG state => same id.
+pub fn new_run_id_with<R: Rng>(now: DateTime<Utc>, rng: &mut R) -> String {
+    let ts = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let rand: String = rng
         .sample_iter(&Alphanumeric)
         .take(10)
         .map(char::from)
@@ -64,29 +167,85 @@ pub fn new_run_id() -> String {
     format!("{ts}-{rand}")
 }
 
+/// Run id for a new run: `$PODCI_RUN_ID` if set (validated), otherwise random.
+pub fn resolve_run_id() -> Result<String> {
+    run_id_from_override(std::env::var(RUN_ID_ENV).ok())
+}
+
+fn run_id_from_override(value: Option<String>) -> Result<String> {
+    match value {
+        Some(id) => {
+            validate_run_id(&id).with_context(|| format!("invalid {RUN_ID_ENV}"))?;
+            Ok(id)
+        }
+        None => Ok(new_run_id()),
+    }
+}
+
+/// A run id names a directory under `<state>/runs/`, so it must be a single
+/// filesystem-safe path component: `[A-Za-z0-9._-]`, at most 128 chars, not `.`/`..`.
+pub fn validate_run_id(id: &str) -> Result<()> {
+    if id.is_empty() || id.len() > 128 {
+        anyhow::bail!("run id must be 1..=128 characters");
+    }
+    if id == "." || id == ".." {
+        anyhow::bail!("run id must not be '.' or '..'");
+    }
+    if let Some(c) = id
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')))
+    {
+        anyhow::bail!("run id contains unsupported character {c:?} (allowed: A-Z a-z 0-9 . _ -)");
+    }
+    Ok(())
+}
+
+/// Explicit state/cache directory overrides (e.g. `--state-dir`/`--cache-dir`).
+///
+/// Overrides are used verbatim; `None` falls back to the XDG resolution in [`state_dirs`].
+#[derive(Debug, Clone, Default)]
+pub struct DirOverrides {
+    pub state_dir: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+}
+
 pub fn state_dirs() -> Result<(PathBuf, PathBuf)> {
+    state_dirs_with(&DirOverrides::default())
+}
+
+pub fn state_dirs_with(overrides: &DirOverrides) -> Result<(PathBuf, PathBuf)> {
     // XDG compliance is intentionally explicit and minimal:
     //   state: $XDG_STATE_HOME/podci (fallback: ~/.local/state/podci)
     //   cache: $XDG_CACHE_HOME/podci (fallback: ~/.cache/podci)
     //
     // Avoid surprising directory layouts derived from (qualifier, org, app)
     // tuples; multiple SRE teams expect the canonical XDG locations.
+    if let (Some(state), Some(cache)) = (&overrides.state_dir, &overrides.cache_dir) {
+        return Ok((state.clone(), cache.clone()));
+    }
     let base = choose_base_strategy().context("unable to resolve home directory")?;
 
-    let state_home = std::env::var_os("XDG_STATE_HOME")
-        .map(PathBuf::from)
-        .or_else(|| base.state_dir())
-        .unwrap_or_else(|| base.home_dir().join(".local").join("state"));
+    let state_dir = match &overrides.state_dir {
+        Some(p) => p.clone(),
+        None => std::env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| base.state_dir())
+            .unwrap_or_else(|| base.home_dir().join(".local").join("state"))
+            .join("podci"),
+    };
 
-    let cache_home = std::env::var_os("XDG_CACHE_HOME")
-        .map(PathBuf::from)
-        .unwrap_or_else(|| base.cache_dir());
+    let cache_dir = match &overrides.cache_dir {
+        Some(p) => p.clone(),
+        None => std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| base.cache_dir())
+            .join("podci"),
+    };
 
-    Ok((state_home.join("podci"), cache_home.join("podci")))
+    Ok((state_dir, cache_dir))
 }
 
-pub async fn write_manifest_v1(run_id: &str, m: &ManifestV1) -> Result<PathBuf> {
-    let (state_dir, _) = state_dirs()?;
+pub async fn write_manifest_v1(state_dir: &Path, run_id: &str, m: &ManifestV1) -> Result<PathBuf> {
     let run_dir = state_dir.join("runs").join(run_id);
     fs::create_dir_all(&run_dir).await?;
 
@@ -101,6 +260,13 @@ pub async fn write_manifest_v1(run_id: &str, m: &ManifestV1) -> Result<PathBuf>
     Ok(path)
 }
 
+pub async fn read_manifest_v1(path: &Path) -> Result<ManifestV1> {
+    let bytes = fs::read(path)
+        .await
+        .with_context(|| format!("read manifest {}", path.display()))?;
+    serde_json::from_slice(&bytes).with_context(|| format!("parse manifest {}", path.display()))
+}
+
 pub fn now_utc_rfc3339() -> String {
     let now: DateTime<Utc> = Utc::now();
     now.to_rfc3339()
@@ -110,6 +276,205 @@ pub fn manifest_schema_v1() -> &'static str {
     "podci-manifest.v1"
 }
 
+/// JSON Schema for `manifest.json` ([`ManifestV1`]), with `$id` set to [`manifest_schema_v1`].
+pub fn manifest_json_schema() -> serde_json::Value {
+    let mut schema = serde_json::to_value(schemars::schema_for!(ManifestV1))
+        .expect("a derived schema always serializes");
+    schema["$id"] = manifest_schema_v1().into();
+    schema
+}
+
+/// Canonical JSON of a manifest file's contents, for hashing and byte-wise comparison:
+/// no insignificant whitespace, object keys sorted (recursively), arrays (e.g. `steps`)
+/// kept in their recorded order, and no trailing newline. Equal manifests always give
+/// equal bytes. Works on the JSON as written, so keys this version doesn't know are
+/// kept, not dropped.
+pub fn canonical_json(manifest_json: &[u8]) -> Result<String> {
+    let v: serde_json::Value =
+        serde_json::from_slice(manifest_json).context("parse manifest JSON")?;
+    let mut out = String::new();
+    write_canonical(&v, &mut out);
+    Ok(out)
+}
+
+fn write_canonical(v: &serde_json::Value, out: &mut String) {
+    use serde_json::Value;
+    match v {
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            // Sorted explicitly: serde_json's map keeps insertion order when a dependency
+            // enables its `preserve_order` feature.
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (k, v)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(k.clone()).to_string());
+                out.push(':');
+                write_canonical(v, out);
+            }
+            out.push('}');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+/// Detached signature of a run's manifest, next to it in `runs/<run_id>/`.
+pub const SIGNATURE_FILE: &str = "manifest.json.sig";
+
+/// ed25519 private key that signs manifests (`run --sign-key`).
+#[cfg(feature = "signing")]
+pub struct ManifestSigningKey(ed25519_dalek::SigningKey);
+
+#[cfg(feature = "signing")]
+impl ManifestSigningKey {
+    /// Parse a PKCS#8 PEM private key (`openssl genpkey -algorithm ed25519`).
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        use ed25519_dalek::pkcs8::DecodePrivateKey;
+        ed25519_dalek::SigningKey::from_pkcs8_pem(pem)
+            .map(Self)
+            .map_err(|e| anyhow::anyhow!("not an ed25519 PKCS#8 PEM private key: {e}"))
+    }
+
+    /// Hex signature of the [`canonical_json`] of a written `manifest.json`, the
+    /// contents of [`SIGNATURE_FILE`].
+    pub fn sign(&self, manifest_json: &[u8]) -> Result<String> {
+        use ed25519_dalek::Signer;
+        let sig = self.0.sign(canonical_json(manifest_json)?.as_bytes());
+        Ok(sig.to_bytes().iter().map(|b| format!("{b:02x}")).collect())
+    }
+}
+
+/// Check a hex `signature` (see [`ManifestSigningKey::sign`]) of the `manifest.json`
+/// contents against a SubjectPublicKeyInfo PEM public key (`openssl pkey -pubout`).
+/// Signatures cover the canonical JSON of the file as written, so reformatting it does
+/// not invalidate them, while any changed, added or removed key does.
+#[cfg(feature = "signing")]
+pub fn verify_manifest_signature(
+    manifest_json: &[u8],
+    public_key_pem: &str,
+    signature: &str,
+) -> Result<()> {
+    use ed25519_dalek::pkcs8::DecodePublicKey;
+    use ed25519_dalek::Verifier;
+    let key = ed25519_dalek::VerifyingKey::from_public_key_pem(public_key_pem)
+        .map_err(|e| anyhow::anyhow!("not an ed25519 PEM public key: {e}"))?;
+    let hex = signature.trim();
+    let bytes: Option<Vec<u8>> = (hex.len() == 128)
+        .then(|| {
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| {
+                    hex.get(i..i + 2)
+                        .and_then(|b| u8::from_str_radix(b, 16).ok())
+                })
+                .collect()
+        })
+        .flatten();
+    let sig = bytes
+        .and_then(|b| ed25519_dalek::Signature::from_slice(&b).ok())
+        .ok_or_else(|| anyhow::anyhow!("malformed signature (expected 128 hex characters)"))?;
+    key.verify(canonical_json(manifest_json)?.as_bytes(), &sig)
+        .map_err(|_| anyhow::anyhow!("signature does not match the manifest"))
+}
+
+/// A field that differs between two manifests: `a` (the baseline run) and `b`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change<T> {
+    pub a: T,
+    pub b: T,
+}
+
+impl<T: PartialEq> Change<T> {
+    fn between(a: T, b: T) -> Option<Self> {
+        (a != b).then_some(Self { a, b })
+    }
+}
+
+/// How one step differs between two manifests (see [`diff_manifests`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StepDiff {
+    pub name: String,
+    /// `"a"` or `"b"` when only that manifest has the step; the other fields are then unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only_in: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub argv: Option<Change<Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<Change<Option<i32>>>,
+}
+
+/// What changed between two runs: `env_id`, `base_image_digest`, and per-step argv and
+/// exit codes. Unchanged fields are `None`; unchanged steps are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ManifestDiff {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_id: Option<Change<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_image_digest: Option<Change<Option<String>>>,
+    pub steps: Vec<StepDiff>,
+}
+
+impl ManifestDiff {
+    pub fn is_empty(&self) -> bool {
+        self.env_id.is_none() && self.base_image_digest.is_none() && self.steps.is_empty()
+    }
+}
+
+/// Compare run `a` (the baseline) with run `b`. Steps are matched by name and listed in
+/// `a`'s order, followed by steps only `b` has.
+pub fn diff_manifests(a: &ManifestV1, b: &ManifestV1) -> ManifestDiff {
+    let mut steps = Vec::new();
+    for sa in &a.steps {
+        let diff = match b.steps.iter().find(|sb| sb.name == sa.name) {
+            Some(sb) => StepDiff {
+                name: sa.name.clone(),
+                only_in: None,
+                argv: Change::between(sa.argv.clone(), sb.argv.clone()),
+                exit_code: Change::between(sa.exit_code, sb.exit_code),
+            },
+            None => StepDiff {
+                name: sa.name.clone(),
+                only_in: Some("a"),
+                argv: None,
+                exit_code: None,
+            },
+        };
+        if diff.only_in.is_some() || diff.argv.is_some() || diff.exit_code.is_some() {
+            steps.push(diff);
+        }
+    }
+    for sb in &b.steps {
+        if !a.steps.iter().any(|sa| sa.name == sb.name) {
+            steps.push(StepDiff {
+                name: sb.name.clone(),
+                only_in: Some("b"),
+                argv: None,
+                exit_code: None,
+            });
+        }
+    }
+    ManifestDiff {
+        env_id: Change::between(a.env_id.clone(), b.env_id.clone()),
+        base_image_digest: Change::between(
+            a.base_image_digest.clone(),
+            b.base_image_digest.clone(),
+        ),
+        steps,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -144,4 +509,219 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(&root);
     }
+
+    #[test]
+    fn seeded_run_id_is_deterministic() {
+        use chrono::TimeZone;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let ts = Utc.with_ymd_and_hms(2026, 2, 19, 9, 51, 12).unwrap();
+        let a = new_run_id_with(ts, &mut StdRng::seed_from_u64(7));
+        let b = new_run_id_with(ts, &mut StdRng::seed_from_u64(7));
+        assert_eq!(a, b);
+        assert!(a.starts_with("20260219T095112Z-"));
+        validate_run_id(&a).unwrap();
+    }
+
+    #[test]
+    fn run_id_override_must_be_filesystem_safe() {
+        assert_eq!(
+            run_id_from_override(Some("test-run_1".to_string())).unwrap(),
+            "test-run_1"
+        );
+        for bad in ["", "..", "a/b", "a b"] {
+            assert!(
+                run_id_from_override(Some(bad.to_string())).is_err(),
+                "{bad:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn state_dirs_with_uses_overrides_verbatim() {
+        let o = DirOverrides {
+            state_dir: Some(PathBuf::from("/tmp/podci-s")),
+            cache_dir: Some(PathBuf::from("/tmp/podci-c")),
+        };
+        let (sd, cd) = state_dirs_with(&o).unwrap();
+        assert_eq!(sd, PathBuf::from("/tmp/podci-s"));
+        assert_eq!(cd, PathBuf::from("/tmp/podci-c"));
+    }
+
+    #[test]
+    fn run_id_timestamp_parses_generated_ids_only() {
+        use chrono::TimeZone;
+
+        let ts = Utc.with_ymd_and_hms(2026, 2, 19, 9, 51, 12).unwrap();
+        let id = new_run_id_with(ts, &mut rand::thread_rng());
+        assert_eq!(run_id_timestamp(&id), Some(ts));
+        assert_eq!(run_id_timestamp("test-run_1"), None);
+    }
+
+    fn fixture(env_id: &str, digest: Option<&str>, steps: serde_json::Value) -> ManifestV1 {
+        serde_json::from_value(serde_json::json!({
+            "schema": "podci-manifest.v1",
+            "podci_version": "0.0.0",
+            "timestamp_utc": "2026-01-01T00:00:00Z",
+            "project": "p",
+            "job": "default",
+            "profile": "dev",
+            "namespace": "ns",
+            "env_id": env_id,
+            "base_image_digest": digest,
+            "steps": steps,
+            "result": { "ok": true, "exit_code": 0, "error": null },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn diff_manifests_reports_env_digest_argv_and_exit_code_changes() {
+        let a = fixture(
+            "e1",
+            Some("sha256:a"),
+            serde_json::json!([
+                { "name": "fmt", "argv": ["cargo", "fmt"], "exit_code": 0 },
+                { "name": "test", "argv": ["cargo", "test"], "exit_code": 0 },
+                { "name": "lint", "argv": ["cargo", "clippy"], "exit_code": 0 },
+            ]),
+        );
+        assert!(diff_manifests(&a, &a).is_empty());
+
+        let b = fixture(
+            "e2",
+            None,
+            serde_json::json!([
+                { "name": "fmt", "argv": ["cargo", "fmt"], "exit_code": 0 },
+                { "name": "test", "argv": ["cargo", "test", "--all"], "exit_code": 101 },
+                { "name": "doc", "argv": ["cargo", "doc"], "exit_code": null },
+            ]),
+        );
+        let d = diff_manifests(&a, &b);
+        assert_eq!(
+            d.env_id,
+            Some(Change {
+                a: "e1".to_string(),
+                b: "e2".to_string()
+            })
+        );
+        assert_eq!(
+            d.base_image_digest,
+            Some(Change {
+                a: Some("sha256:a".to_string()),
+                b: None
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&d.steps).unwrap(),
+            serde_json::json!([
+                {
+                    "name": "test",
+                    "argv": { "a": ["cargo", "test"], "b": ["cargo", "test", "--all"] },
+                    "exit_code": { "a": 0, "b": 101 },
+                },
+                { "name": "lint", "only_in": "a" },
+                { "name": "doc", "only_in": "b" },
+            ])
+        );
+    }
+
+    #[test]
+    fn canonical_json_is_compact_sorted_and_stable() {
+        let steps = serde_json::json!([
+            { "name": "test", "argv": ["cargo", "test"], "exit_code": 0,
+              "env": { "Z": "1", "A": "2" } },
+            { "name": "fmt", "argv": ["cargo", "fmt"], "exit_code": 0 },
+        ]);
+        let m = fixture("e1", Some("sha256:a"), steps);
+        let c = canonical_json(&serde_json::to_vec_pretty(&m).unwrap()).unwrap();
+        assert!(!c.contains(char::is_whitespace), "{c}");
+        assert!(
+            c.starts_with(r#"{"base_image_digest":"sha256:a","base_image_digest_status":null,"#)
+        );
+        assert!(c.contains(r#""env":{"A":"2","Z":"1"}"#), "{c}");
+        // Step order is meaningful and kept.
+        assert!(c.find(r#""name":"test""#) < c.find(r#""name":"fmt""#));
+
+        // Same content in another layout gives the same bytes.
+        assert_eq!(canonical_json(&serde_json::to_vec(&m).unwrap()).unwrap(), c);
+        let other = fixture("e2", Some("sha256:a"), serde_json::json!([]));
+        assert_ne!(
+            canonical_json(&serde_json::to_vec(&other).unwrap()).unwrap(),
+            c
+        );
+        // Keys unknown to ManifestV1 are kept.
+        let mut v = serde_json::to_value(&m).unwrap();
+        v["extra"] = serde_json::json!({ "b": 1, "a": null });
+        let with_extra = canonical_json(&serde_json::to_vec(&v).unwrap()).unwrap();
+        assert!(
+            with_extra.contains(r#""extra":{"a":null,"b":1}"#),
+            "{with_extra}"
+        );
+        assert!(canonical_json(b"not json").is_err());
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn signatures_cover_canonical_json_and_detect_changes() {
+        use ed25519_dalek::pkcs8::{EncodePrivateKey, EncodePublicKey};
+
+        let sk = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let key =
+            ManifestSigningKey::from_pem(&sk.to_pkcs8_pem(Default::default()).unwrap()).unwrap();
+        let public = sk
+            .verifying_key()
+            .to_public_key_pem(Default::default())
+            .unwrap();
+        let m = fixture(
+            "e1",
+            None,
+            serde_json::json!([{ "name": "fmt", "argv": ["cargo", "fmt"], "exit_code": 0 }]),
+        );
+        let written = serde_json::to_vec_pretty(&m).unwrap();
+        let sig = key.sign(&written).unwrap();
+        assert_eq!(sig.len(), 128);
+
+        // Reformatting the file (compact here) keeps the signature valid.
+        let compact = serde_json::to_vec(&m).unwrap();
+        verify_manifest_signature(&compact, &public, &format!("{sig}\n")).unwrap();
+
+        let mut tampered = m.clone();
+        tampered.steps[0].exit_code = Some(1);
+        let tampered = serde_json::to_vec_pretty(&tampered).unwrap();
+        let err = verify_manifest_signature(&tampered, &public, &sig).unwrap_err();
+        assert_eq!(err.to_string(), "signature does not match the manifest");
+        // An added key, even one ManifestV1 would ignore (or a `null`), is a change too.
+        let mut v: serde_json::Value = serde_json::from_slice(&written).unwrap();
+        v["approved_by"] = serde_json::Value::Null;
+        let added = serde_json::to_vec_pretty(&v).unwrap();
+        assert!(verify_manifest_signature(&added, &public, &sig).is_err());
+
+        let other = ed25519_dalek::SigningKey::from_bytes(&[8; 32])
+            .verifying_key()
+            .to_public_key_pem(Default::default())
+            .unwrap();
+        assert!(verify_manifest_signature(&written, &other, &sig).is_err());
+        assert!(verify_manifest_signature(&written, &public, "abc").is_err());
+        assert!(ManifestSigningKey::from_pem(&public).is_err());
+    }
+
+    #[test]
+    fn manifest_json_schema_describes_v1() {
+        let schema = manifest_json_schema();
+        assert_eq!(schema["$id"], "podci-manifest.v1");
+        let required: Vec<&str> = schema["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|v| v.as_str())
+            .collect();
+        for field in ["schema", "env_id", "steps", "result"] {
+            assert!(required.contains(&field), "{field}");
+        }
+        // Optional (`serde(default)`) fields stay optional so older manifests validate.
+        assert!(!required.contains(&"run_id"));
+        assert!(schema["properties"]["labels"].is_object());
+        assert!(schema["definitions"]["ManifestStepV1"]["properties"]["soft_failed"].is_object());
+    }
 }
@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // Copyright (c) 2026 Richard Majewski - Varanid Works
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use blake3::Hasher;
 use serde::Serialize;
 
@@ -16,19 +16,20 @@ pub fn blake3_fingerprint<T: Serialize>(value: &T) -> Result<String> {
     Ok(h.finalize().to_hex().to_string())
 }
 
+// Conservative: only allow [a-z0-9_-.], replace everything else.
+fn safe(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
 pub fn namespace_from(project: &str, job: &str, env_id: &str) -> String {
-    // Conservative: only allow [a-z0-9_-.], replace everything else.
-    fn safe(s: &str) -> String {
-        s.chars()
-            .map(|c| {
-                if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
-                    c.to_ascii_lowercase()
-                } else {
-                    '_'
-                }
-            })
-            .collect()
-    }
     format!(
         "podci_{}_{}_{}",
         safe(project),
@@ -36,3 +37,82 @@ pub fn namespace_from(project: &str, job: &str, env_id: &str) -> String {
         &env_id[..12.min(env_id.len())]
     )
 }
+
+/// Namespace for caches shared by every job/env_id using the same profile.
+///
+/// The `_shared` suffix cannot collide with [`namespace_from`], whose last
+/// component is always a hex env_id prefix.
+pub fn profile_namespace_from(project: &str, profile: &str) -> String {
+    format!("podci_{}_{}_shared", safe(project), safe(profile))
+}
+
+/// Placeholders accepted by [`namespace_from_template`].
+const TEMPLATE_PLACEHOLDERS: &[&str] = &[
+    "prefix", "project", "job", "profile", "env8", "env12", "env_id",
+];
+
+/// Reject templates with unknown/unclosed placeholders, characters outside
+/// `[a-z0-9_.-]`, or no env_id placeholder (`{env8}`, `{env12}`, `{env_id}`).
+///
+/// The env_id portion is mandatory: without it, jobs whose environments differ
+/// would share cache volumes.
+pub fn validate_namespace_template(template: &str) -> Result<()> {
+    let mut has_env = false;
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        check_template_literal(template, &rest[..open])?;
+        let Some(close) = rest[open..].find('}') else {
+            bail!("namespace_template '{template}' has an unclosed '{{'");
+        };
+        let name = &rest[open + 1..open + close];
+        if !TEMPLATE_PLACEHOLDERS.contains(&name) {
+            bail!(
+                "namespace_template '{template}' uses unknown placeholder '{{{name}}}' (expected one of: {})",
+                TEMPLATE_PLACEHOLDERS.join(", ")
+            );
+        }
+        has_env |= name.starts_with("env");
+        rest = &rest[open + close + 1..];
+    }
+    check_template_literal(template, rest)?;
+    if !has_env {
+        bail!("namespace_template '{template}' must include {{env8}}, {{env12}}, or {{env_id}}");
+    }
+    Ok(())
+}
+
+fn check_template_literal(template: &str, lit: &str) -> Result<()> {
+    if let Some(c) = lit
+        .chars()
+        .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-' | '.')))
+    {
+        bail!("namespace_template '{template}' contains '{c}' (allowed: a-z 0-9 _ - . and placeholders)");
+    }
+    Ok(())
+}
+
+/// Namespace rendered from a user `namespace_template` (see [`validate_namespace_template`]).
+///
+/// Values are sanitized like [`namespace_from`]; the result must be a valid podman
+/// volume name prefix (starts with an alphanumeric).
+pub fn namespace_from_template(
+    template: &str,
+    project: &str,
+    job: &str,
+    profile: &str,
+    env_id: &str,
+) -> Result<String> {
+    validate_namespace_template(template)?;
+    let ns = template
+        .replace("{prefix}", "podci")
+        .replace("{project}", &safe(project))
+        .replace("{job}", &safe(job))
+        .replace("{profile}", &safe(profile))
+        .replace("{env8}", &env_id[..8.min(env_id.len())])
+        .replace("{env12}", &env_id[..12.min(env_id.len())])
+        .replace("{env_id}", env_id);
+    if !ns.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        bail!("namespace_template '{template}' renders '{ns}', which must start with a letter or digit");
+    }
+    Ok(ns)
+}
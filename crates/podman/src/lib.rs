@@ -1,13 +1,15 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 // Copyright (c) 2026 Richard Majewski - Varanid Works
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::error::Error as StdError;
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::process::Command;
 use tokio::time::timeout;
@@ -16,6 +18,129 @@ use tracing::{info, warn};
 #[derive(Debug, Clone)]
 pub struct Podman {
     pub path: PathBuf,
+    /// Receives start/exit notifications for every podman invocation (none by default).
+    pub observer: Option<Arc<dyn PodmanObserver>>,
+    /// Appends every invocation to a JSONL trace file (see [`TRACE_ENV`]).
+    trace: Option<Arc<Mutex<std::fs::File>>>,
+    /// Serves invocations from a recorded trace instead of running podman (see [`REPLAY_ENV`]).
+    replay: Option<Arc<Replay>>,
+}
+
+/// When set, every podman invocation is appended to this JSONL file as a [`TraceEntry`].
+pub const TRACE_ENV: &str = "PODCI_PODMAN_TRACE";
+
+/// When set, podman is not run; results are fed back, in order, from this trace file.
+pub const REPLAY_ENV: &str = "PODCI_PODMAN_REPLAY";
+
+/// Per-stream cap on traced output (the tail is kept, as in error messages).
+const TRACE_OUTPUT_MAX: usize = 64 * 1024;
+
+/// One podman invocation, as written to (and replayed from) a trace file.
+///
+/// `--env KEY=VALUE` arguments are recorded as `KEY=***`; the values never reach the file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub args: Vec<String>,
+    pub exit_code: i32,
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+}
+
+#[derive(Debug)]
+struct Replay {
+    path: PathBuf,
+    entries: Mutex<VecDeque<TraceEntry>>,
+}
+
+/// Structured lifecycle notification for one podman invocation.
+#[derive(Debug, Clone)]
+pub enum PodmanEvent {
+    Start {
+        cmd: String,
+    },
+    Exit {
+        cmd: String,
+        exit_code: i32,
+        duration: Duration,
+    },
+    /// One completed `STEP` of an image build (only with build progress parsing).
+    BuildStep(BuildStep),
+}
+
+/// A parsed `podman build` step, e.g. `STEP 2/5: RUN dnf -y install gcc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildStep {
+    /// 1-based step number.
+    pub step: u32,
+    /// Total steps, when podman reports `N/M` (older buildah prints only `N`).
+    pub total: Option<u32>,
+    /// The Containerfile instruction as printed by podman.
+    pub instruction: String,
+    /// Whether the layer came from the build cache (`--> Using cache ...`).
+    pub cached: bool,
+}
+
+/// Incremental parser for `podman build` output lines.
+///
+/// A step is only complete once the next `STEP` line (or the end of output) is seen,
+/// because the cache hit marker follows the step header.
+#[derive(Debug, Default)]
+pub struct BuildProgressParser {
+    current: Option<BuildStep>,
+}
+
+impl BuildProgressParser {
+    /// Feed one output line; returns the previous step if this line starts a new one.
+    pub fn push_line(&mut self, line: &str) -> Option<BuildStep> {
+        let line = line.trim();
+        if let Some(step) = parse_build_step_header(line) {
+            return self.current.replace(step);
+        }
+        if line.starts_with("--> Using cache") {
+            if let Some(cur) = &mut self.current {
+                cur.cached = true;
+            }
+        }
+        None
+    }
+
+    /// Flush the last step at end of output.
+    pub fn finish(&mut self) -> Option<BuildStep> {
+        self.current.take()
+    }
+}
+
+/// Parse `STEP N/M: INSTR`, tolerating `STEP N: INSTR`, a multi-stage `[1/2] ` prefix
+/// and docker-style `Step N/M : INSTR`.
+fn parse_build_step_header(line: &str) -> Option<BuildStep> {
+    let mut rest = line;
+    if rest.starts_with('[') {
+        rest = rest.split_once("] ")?.1;
+    }
+    let rest = rest
+        .strip_prefix("STEP ")
+        .or_else(|| rest.strip_prefix("Step "))?;
+    let (counter, instruction) = rest.split_once(':')?;
+    let (step, total) = match counter.trim().split_once('/') {
+        Some((n, m)) => (n.parse().ok()?, Some(m.parse().ok()?)),
+        None => (counter.trim().parse().ok()?, None),
+    };
+    Some(BuildStep {
+        step,
+        total,
+        instruction: instruction.trim().to_string(),
+        cached: false,
+    })
+}
+
+/// Callback for [`PodmanEvent`]s, e.g. to write an events log or emit trace spans.
+///
+/// Called inline on the invoking task; implementations should be quick and must not panic.
+pub trait PodmanObserver: fmt::Debug + Send + Sync {
+    fn on_event(&self, event: &PodmanEvent);
 }
 
 #[derive(Debug, Clone)]
@@ -26,10 +151,102 @@ pub struct ExecResult {
     pub stderr: Vec<u8>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PodmanVersion {
+    /// Client version (e.g. `5.2.0`).
+    pub client_version: String,
+    /// Client API version, when reported by `podman version --format json`.
+    pub api_version: Option<String>,
+    /// Human-readable version line, `podman version <client_version>`.
+    pub raw: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct VolumeInfo {
     pub created_at: Option<DateTime<Utc>>,
     pub labels: std::collections::BTreeMap<String, String>,
+    /// Host path of the volume's data (see [`Podman::volume_size_bytes`]).
+    pub mountpoint: Option<String>,
+}
+
+/// A local image as listed by `podman images`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub id: String,
+    /// Repository tags (`localhost/podci-rust-debian:v1`); empty for dangling images.
+    pub names: Vec<String>,
+    pub digest: Option<String>,
+    pub size: u64,
+    /// Creation time; `None` if podman did not report one.
+    pub created: Option<DateTime<Utc>>,
+    pub labels: std::collections::BTreeMap<String, String>,
+}
+
+/// Outcome of [`Podman::prune_images_unused`] and [`Podman::remove_images`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImagePruneReport {
+    /// Removed images, by first name (or id when untagged).
+    pub removed: Vec<String>,
+    /// Sum of the removed images' sizes as podman reports them. Layers shared with
+    /// images that remain are not freed, so this is an upper bound.
+    pub bytes_reclaimed: u64,
+}
+
+/// Label podCI puts on images it builds.
+pub const MANAGED_LABEL: (&str, &str) = ("podci.managed", "true");
+
+/// Label holding the podCI version that built an image (see [`ImageBuild::podci_version`]).
+pub const VERSION_LABEL: &str = "podci.version";
+
+/// Label podCI puts on step containers, holding the run id (see
+/// [`Podman::stop_containers_by_label`]).
+pub const RUN_ID_LABEL: &str = "podci.run_id";
+
+/// Name prefix of template images podCI builds (`localhost/podci-<template>:<tag>`).
+pub const TEMPLATE_IMAGE_PREFIX: &str = "localhost/podci-";
+
+/// Which output stream a line came from (see [`Podman::run_capture_streaming`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// When images are pulled (`podman build --pull=...`, `podman pull`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PullPolicy {
+    /// Never contact a registry; base layers must already be local.
+    Never,
+    /// Pull only what is missing locally (podman's default).
+    #[default]
+    Missing,
+    /// Always check the registry for newer images.
+    Always,
+}
+
+impl PullPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Never => "never",
+            Self::Missing => "missing",
+            Self::Always => "always",
+        }
+    }
+}
+
+/// Inputs to [`Podman::build_image`].
+#[derive(Debug, Clone, Copy)]
+pub struct ImageBuild<'a> {
+    pub context_dir: &'a Path,
+    pub containerfile_path: &'a Path,
+    pub tag: &'a str,
+    /// Recorded as the [`VERSION_LABEL`] label, so `podci prune` can tell which
+    /// podCI release an image belongs to.
+    pub podci_version: &'a str,
+    pub pull: PullPolicy,
+    pub no_cache: bool,
+    /// Also parse build output into [`PodmanEvent::BuildStep`] events.
+    pub progress: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -41,6 +258,7 @@ pub enum ExecMode {
 pub enum PodmanErrorKind {
     NotInstalled,
     PermissionDenied,
+    AuthRequired,
     StorageError,
     CommandFailed,
     Unknown,
@@ -101,9 +319,184 @@ impl fmt::Display for PodmanRunError {
 impl StdError for PodmanRunError {}
 
 impl Podman {
+    /// Find podman on PATH, honouring [`TRACE_ENV`] and [`REPLAY_ENV`].
     pub fn detect() -> Result<Self> {
+        let trace = std::env::var_os(TRACE_ENV).filter(|v| !v.is_empty());
+        let replay = std::env::var_os(REPLAY_ENV).filter(|v| !v.is_empty());
+        if let Some(replay) = replay {
+            if trace.is_some() {
+                bail!("{TRACE_ENV} and {REPLAY_ENV} cannot be used together");
+            }
+            // A replay never spawns podman, so it works on hosts without it.
+            let path = which::which("podman").unwrap_or_else(|_| PathBuf::from("podman"));
+            return Self::new(path).with_replay(Path::new(&replay));
+        }
         let path = which::which("podman").context("find podman on PATH")?;
-        Ok(Self { path })
+        match trace {
+            Some(trace) => Self::new(path).with_trace(Path::new(&trace)),
+            None => Ok(Self::new(path)),
+        }
+    }
+
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            observer: None,
+            trace: None,
+            replay: None,
+        }
+    }
+
+    /// Append a [`TraceEntry`] line to `path` for every invocation.
+    pub fn with_trace(mut self, path: &Path) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("open podman trace {}", path.display()))?;
+        self.trace = Some(Arc::new(Mutex::new(file)));
+        Ok(self)
+    }
+
+    /// Answer invocations from the trace at `path`, in recorded order, instead of running podman.
+    pub fn with_replay(mut self, path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("read podman trace {}", path.display()))?;
+        let mut entries = VecDeque::new();
+        for (i, line) in raw.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: TraceEntry = serde_json::from_str(line)
+                .with_context(|| format!("parse {}:{}", path.display(), i + 1))?;
+            entries.push_back(entry);
+        }
+        self.replay = Some(Arc::new(Replay {
+            path: path.to_path_buf(),
+            entries: Mutex::new(entries),
+        }));
+        Ok(self)
+    }
+
+    pub fn with_observer(mut self, observer: Arc<dyn PodmanObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Command line for logs and observers, with `--env` values masked like the trace.
+    fn event_cmd(&self, args: &[&str]) -> String {
+        let masked = trace_args(args);
+        let refs: Vec<&str> = masked.iter().map(String::as_str).collect();
+        format_cmd(&self.path, &refs)
+    }
+
+    fn emit_start(&self, args: &[&str]) {
+        let cmd = self.event_cmd(args);
+        info!(cmd=%cmd, event="podman_start");
+        if let Some(o) = &self.observer {
+            o.on_event(&PodmanEvent::Start { cmd });
+        }
+    }
+
+    fn emit_exit(
+        &self,
+        args: &[&str],
+        exit_code: i32,
+        duration: Duration,
+        stdout: &[u8],
+        stderr: &[u8],
+    ) {
+        if self.replay.is_none() {
+            self.write_trace(args, exit_code, duration, stdout, stderr);
+        }
+        let cmd = self.event_cmd(args);
+        info!(cmd=%cmd, exit_code, duration_ms=%duration.as_millis(), event="podman_exit");
+        if let Some(o) = &self.observer {
+            o.on_event(&PodmanEvent::Exit {
+                cmd,
+                exit_code,
+                duration,
+            });
+        }
+    }
+
+    fn write_trace(
+        &self,
+        args: &[&str],
+        exit_code: i32,
+        duration: Duration,
+        stdout: &[u8],
+        stderr: &[u8],
+    ) {
+        use std::io::Write;
+
+        let Some(trace) = &self.trace else {
+            return;
+        };
+        let entry = TraceEntry {
+            args: trace_args(args),
+            exit_code,
+            duration_ms: duration.as_millis() as u64,
+            stdout: trunc_utf8_lossy(stdout, TRACE_OUTPUT_MAX),
+            stderr: trunc_utf8_lossy(stderr, TRACE_OUTPUT_MAX),
+        };
+        let res = serde_json::to_string(&entry)
+            .map_err(std::io::Error::from)
+            .and_then(|line| {
+                let mut f = trace.lock().unwrap_or_else(|e| e.into_inner());
+                writeln!(f, "{line}")
+            });
+        // Tracing is a debugging aid; never fail the invocation over it.
+        if let Err(e) = res {
+            warn!(error=%e, event="podman_trace_write_failed");
+        }
+    }
+
+    /// In replay mode, the recorded result for the next invocation (`None` otherwise).
+    fn replayed(&self, args: &[&str]) -> Result<Option<std::process::Output>> {
+        use std::os::unix::process::ExitStatusExt;
+
+        let Some(replay) = &self.replay else {
+            return Ok(None);
+        };
+        let entry = replay
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop_front();
+        let Some(entry) = entry else {
+            bail!(
+                "podman replay {} has no entry left for: {}",
+                replay.path.display(),
+                format_cmd(&self.path, args)
+            );
+        };
+        let args = trace_args(args);
+        if entry.args != args {
+            warn!(
+                expected = %entry.args.join(" "),
+                actual = %args.join(" "),
+                event = "podman_replay_mismatch"
+            );
+        }
+        Ok(Some(std::process::Output {
+            status: std::process::ExitStatus::from_raw((entry.exit_code & 0xff) << 8),
+            stdout: entry.stdout.into_bytes(),
+            stderr: entry.stderr.into_bytes(),
+        }))
+    }
+
+    fn emit_build_step(&self, step: BuildStep) {
+        info!(
+            step = step.step,
+            total = ?step.total,
+            cached = step.cached,
+            instruction = %step.instruction,
+            event = "image_build_step"
+        );
+        if let Some(o) = &self.observer {
+            o.on_event(&PodmanEvent::BuildStep(step));
+        }
     }
 
     pub async fn run_capture(
@@ -133,12 +526,16 @@ impl Podman {
         cmd.stdin(Stdio::null());
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
+        // A cancelled caller (e.g. `podci cancel` during a pull) drops the future.
+        cmd.kill_on_drop(true);
 
         let start = Instant::now();
-        info!(cmd=%format_cmd(&self.path, args), event="podman_start");
+        self.emit_start(args);
 
         let fut = cmd.output();
-        let out = if let Some(td) = timeout_dur {
+        let out = if let Some(out) = self.replayed(args)? {
+            out
+        } else if let Some(td) = timeout_dur {
             timeout(td, fut).await.context("podman timed out")??
         } else {
             fut.await?
@@ -178,12 +575,16 @@ impl Podman {
         cmd.stdin(Stdio::null());
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
+        // On timeout the output future is dropped; make sure podman goes with it.
+        cmd.kill_on_drop(true);
 
         let start = Instant::now();
-        info!(cmd=%format_cmd(&self.path, args), event="podman_start");
+        self.emit_start(args);
 
         let fut = cmd.output();
-        let out = if let Some(td) = timeout_dur {
+        let out = if let Some(out) = self.replayed(args)? {
+            out
+        } else if let Some(td) = timeout_dur {
             timeout(td, fut).await.context("podman timed out")??
         } else {
             fut.await?
@@ -210,12 +611,17 @@ impl Podman {
         cmd.stdin(Stdio::null());
         cmd.stdout(Stdio::inherit());
         cmd.stderr(Stdio::inherit());
+        // A cancelled caller (e.g. `podci cancel` during a build) drops the future.
+        cmd.kill_on_drop(true);
 
         let start = Instant::now();
-        info!(cmd=%format_cmd(&self.path, args), event="podman_start");
+        self.emit_start(args);
 
         let fut = cmd.status();
-        let status = if let Some(td) = timeout_dur {
+        let status = if let Some(out) = self.replayed(args)? {
+            replay_to_terminal(&out)?;
+            out.status
+        } else if let Some(td) = timeout_dur {
             timeout(td, fut).await.context("podman timed out")??
         } else {
             fut.await?
@@ -223,7 +629,7 @@ impl Podman {
 
         let duration = start.elapsed();
         let exit_code = status.code().unwrap_or(1);
-        info!(cmd=%format_cmd(&self.path, args), exit_code, duration_ms=%duration.as_millis(), event="podman_exit");
+        self.emit_exit(args, exit_code, duration, &[], &[]);
 
         if !status.success() {
             // We don't have stderr bytes in inherit mode; provide a short classification-only error.
@@ -247,6 +653,186 @@ impl Podman {
         })
     }
 
+    /// Run `podman` attached to the caller's terminal (stdin/stdout/stderr inherited).
+    ///
+    /// Intended for `podman run -it` of a single interactive step. Output is not captured
+    /// (empty `stdout`/`stderr`) and a non-zero exit status is returned, not turned into an error.
+    pub async fn run_attached(
+        &self,
+        args: &[&str],
+        env: &[(&str, &str)],
+        timeout_dur: Option<Duration>,
+    ) -> Result<ExecResult> {
+        let mut cmd = Command::new(&self.path);
+        cmd.args(args);
+        for (k, v) in env {
+            cmd.env(k, v);
+        }
+        cmd.stdin(Stdio::inherit());
+        cmd.stdout(Stdio::inherit());
+        cmd.stderr(Stdio::inherit());
+        cmd.kill_on_drop(true);
+
+        let start = Instant::now();
+        self.emit_start(args);
+
+        let fut = cmd.status();
+        let status = if let Some(out) = self.replayed(args)? {
+            replay_to_terminal(&out)?;
+            out.status
+        } else if let Some(td) = timeout_dur {
+            timeout(td, fut).await.context("podman timed out")??
+        } else {
+            fut.await?
+        };
+
+        let duration = start.elapsed();
+        let exit_code = status.code().unwrap_or(1);
+        self.emit_exit(args, exit_code, duration, &[], &[]);
+
+        Ok(ExecResult {
+            exit_code,
+            duration,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        })
+    }
+
+    /// Like [`Podman::run_capture_with_env_allow_failure`], but hands every output line
+    /// (including its `\n`; a final unterminated line as-is) to `on_line` as it arrives.
+    /// The returned [`ExecResult`] holds the same exit code and bytes as the buffered call.
+    pub async fn run_capture_streaming(
+        &self,
+        args: &[&str],
+        env: &[(&str, &str)],
+        cwd: Option<&std::path::Path>,
+        timeout_dur: Option<Duration>,
+        on_line: &mut (dyn FnMut(OutputStream, &[u8]) + Send),
+    ) -> Result<ExecResult> {
+        let mut cmd = Command::new(&self.path);
+        cmd.args(args);
+        for (k, v) in env {
+            cmd.env(k, v);
+        }
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        cmd.kill_on_drop(true);
+
+        let start = Instant::now();
+        self.emit_start(args);
+
+        let (status, stdout, stderr) = if let Some(out) = self.replayed(args)? {
+            let (stdout, stderr) =
+                stream_lines(out.stdout.as_slice(), out.stderr.as_slice(), on_line).await?;
+            (out.status, stdout, stderr)
+        } else {
+            let mut child = cmd.spawn().context("spawn podman")?;
+            let child_out = child.stdout.take().context("podman stdout not piped")?;
+            let child_err = child.stderr.take().context("podman stderr not piped")?;
+
+            let fut = async {
+                let (stdout, stderr) = stream_lines(child_out, child_err, on_line).await?;
+                let status = child.wait().await?;
+                Ok::<_, anyhow::Error>((status, stdout, stderr))
+            };
+            if let Some(td) = timeout_dur {
+                timeout(td, fut).await.context("podman timed out")??
+            } else {
+                fut.await?
+            }
+        };
+
+        self.finish_capture_allow_failure(
+            args,
+            std::process::Output {
+                status,
+                stdout,
+                stderr,
+            },
+            start,
+        )
+    }
+
+    /// Like [`Podman::run_inherit`], but tees output: stdout/stderr are shown live on
+    /// the terminal, appended to `log_path` (interleaved, in arrival order), and returned
+    /// in the [`ExecResult`].
+    pub async fn run_inherit_tee(
+        &self,
+        args: &[&str],
+        env: &[(&str, &str)],
+        cwd: Option<&std::path::Path>,
+        timeout_dur: Option<Duration>,
+        log_path: &std::path::Path,
+    ) -> Result<ExecResult> {
+        let mut cmd = Command::new(&self.path);
+        cmd.args(args);
+        for (k, v) in env {
+            cmd.env(k, v);
+        }
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        cmd.kill_on_drop(true);
+
+        let mut log = tokio::fs::File::create(log_path)
+            .await
+            .with_context(|| format!("create {}", log_path.display()))?;
+
+        let start = Instant::now();
+        self.emit_start(args);
+
+        let (status, stdout, stderr) = if let Some(out) = self.replayed(args)? {
+            let (stdout, stderr) =
+                tee_streams(out.stdout.as_slice(), out.stderr.as_slice(), &mut log).await?;
+            (out.status, stdout, stderr)
+        } else {
+            let mut child = cmd.spawn().context("spawn podman")?;
+            let child_out = child.stdout.take().context("podman stdout not piped")?;
+            let child_err = child.stderr.take().context("podman stderr not piped")?;
+
+            let fut = async {
+                let (stdout, stderr) = tee_streams(child_out, child_err, &mut log).await?;
+                let status = child.wait().await?;
+                Ok::<_, anyhow::Error>((status, stdout, stderr))
+            };
+            if let Some(td) = timeout_dur {
+                timeout(td, fut).await.context("podman timed out")??
+            } else {
+                fut.await?
+            }
+        };
+
+        let duration = start.elapsed();
+        let exit_code = status.code().unwrap_or(1);
+        self.emit_exit(args, exit_code, duration, &stdout, &stderr);
+
+        if !status.success() {
+            let err = PodmanRunError::from_exec(
+                format_cmd(&self.path, args),
+                exit_code,
+                &stdout,
+                &stderr,
+                Some(log_path.to_path_buf()),
+                Some(log_path.to_path_buf()),
+            );
+            return Err(anyhow::Error::new(err));
+        }
+
+        Ok(ExecResult {
+            exit_code,
+            duration,
+            stdout,
+            stderr,
+        })
+    }
+
     fn finish_capture(
         &self,
         args: &[&str],
@@ -255,7 +841,7 @@ impl Podman {
     ) -> Result<ExecResult> {
         let duration = start.elapsed();
         let exit_code = out.status.code().unwrap_or(1);
-        info!(cmd=%format_cmd(&self.path, args), exit_code, duration_ms=%duration.as_millis(), event="podman_exit");
+        self.emit_exit(args, exit_code, duration, &out.stdout, &out.stderr);
 
         if !out.status.success() {
             let kind = classify_failure(exit_code, &out.stderr);
@@ -287,7 +873,7 @@ impl Podman {
     ) -> Result<ExecResult> {
         let duration = start.elapsed();
         let exit_code = out.status.code().unwrap_or(1);
-        info!(cmd=%format_cmd(&self.path, args), exit_code, duration_ms=%duration.as_millis(), event="podman_exit");
+        self.emit_exit(args, exit_code, duration, &out.stdout, &out.stderr);
 
         Ok(ExecResult {
             exit_code,
@@ -308,16 +894,18 @@ impl Podman {
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
         let start = Instant::now();
-        info!(cmd=%format_cmd(&self.path, args), event="podman_start");
+        self.emit_start(args);
         let fut = cmd.output();
-        let out = if let Some(td) = timeout_dur {
+        let out = if let Some(out) = self.replayed(args)? {
+            out
+        } else if let Some(td) = timeout_dur {
             timeout(td, fut).await.context("podman timed out")??
         } else {
             fut.await?
         };
         let duration = start.elapsed();
         let exit_code = out.status.code().unwrap_or(1);
-        info!(cmd=%format_cmd(&self.path, args), exit_code, duration_ms=%duration.as_millis(), event="podman_exit");
+        self.emit_exit(args, exit_code, duration, &out.stdout, &out.stderr);
         Ok(ExecResult {
             exit_code,
             duration,
@@ -387,6 +975,8 @@ impl Podman {
             created_at: Option<String>,
             #[serde(rename = "Labels")]
             labels: Option<std::collections::BTreeMap<String, String>>,
+            #[serde(rename = "Mountpoint", default)]
+            mountpoint: Option<String>,
         }
 
         let rows: Vec<VolInspect> =
@@ -395,6 +985,7 @@ impl Podman {
             return Ok(VolumeInfo {
                 created_at: None,
                 labels: Default::default(),
+                mountpoint: None,
             });
         };
 
@@ -415,6 +1006,30 @@ impl Podman {
         Ok(VolumeInfo {
             created_at,
             labels: row.labels.unwrap_or_default(),
+            mountpoint: row.mountpoint.filter(|m| !m.is_empty()),
+        })
+    }
+
+    /// Disk usage of a volume, measured with `du -sb` on its mountpoint inside
+    /// `podman unshare` (rootless volume contents are owned by subordinate ids).
+    /// podman itself reports no per-volume size in `volume inspect`.
+    pub async fn volume_size_bytes(&self, name: &str) -> Result<u64> {
+        let info = self.volume_inspect_info(name).await?;
+        let Some(mountpoint) = info.mountpoint else {
+            bail!("volume {name} has no mountpoint");
+        };
+        // du exits non-zero when some entries are unreadable but still prints a total.
+        let r = self
+            .run_capture_allow_fail(
+                ["unshare", "du", "-sb", mountpoint.as_str()].as_slice(),
+                Some(Duration::from_secs(120)),
+            )
+            .await?;
+        parse_du_bytes(&r.stdout).with_context(|| {
+            format!(
+                "measure volume {name}: {}",
+                trunc_utf8_lossy(&r.stderr, 512).trim()
+            )
         })
     }
 
@@ -437,6 +1052,37 @@ impl Podman {
         Ok(rows.into_iter().map(|r| r.name).collect())
     }
 
+    /// Stop running containers labeled `key=value`, giving each `grace` before SIGKILL.
+    /// Returns how many were found.
+    pub async fn stop_containers_by_label(
+        &self,
+        key: &str,
+        value: &str,
+        grace: Duration,
+    ) -> Result<usize> {
+        let filter = format!("label={key}={value}");
+        let r = self
+            .run_capture(
+                ["ps", "-q", "--filter", filter.as_str()].as_slice(),
+                Some(Duration::from_secs(30)),
+            )
+            .await?;
+        let ids: Vec<String> = String::from_utf8_lossy(&r.stdout)
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let time = grace.as_secs().to_string();
+        let mut args = vec!["stop", "--time", time.as_str()];
+        args.extend(ids.iter().map(String::as_str));
+        // Containers may exit on their own meanwhile; `stop` of a gone one is fine.
+        self.run_capture_allow_fail(args.as_slice(), Some(grace + Duration::from_secs(30)))
+            .await?;
+        Ok(ids.len())
+    }
+
     pub async fn volume_list_by_label(&self, key: &str, value: &str) -> Result<Vec<String>> {
         let filter = format!("label={key}={value}");
         let r = self
@@ -465,6 +1111,67 @@ impl Podman {
         Ok(rows.into_iter().map(|r| r.name).collect())
     }
 
+    /// Local images carrying label `key=value`.
+    pub async fn image_list_by_label(&self, key: &str, value: &str) -> Result<Vec<ImageInfo>> {
+        let filter = format!("label={key}={value}");
+        let r = self
+            .run_capture(
+                ["images", "--filter", filter.as_str(), "--format", "json"].as_slice(),
+                Some(Duration::from_secs(30)),
+            )
+            .await?;
+        parse_images_json(&r.stdout)
+    }
+
+    /// Remove podCI template images (`localhost/podci-*` labeled `podci.managed=true`)
+    /// that nothing in `keep` refers to. `keep` holds the image ids, digests, or names
+    /// still needed, e.g. the base image digests recorded by retained namespaces' manifests.
+    ///
+    /// Unlike `podman image prune`, images podCI did not build are never touched. An image
+    /// podman refuses to remove (e.g. used by a container) is logged and skipped.
+    pub async fn prune_images_unused(
+        &self,
+        keep: &std::collections::BTreeSet<String>,
+    ) -> Result<ImagePruneReport> {
+        let images = self
+            .image_list_by_label(MANAGED_LABEL.0, MANAGED_LABEL.1)
+            .await?;
+        self.remove_images(&select_unused_images(images, keep))
+            .await
+    }
+
+    /// [`Podman::image_remove`] each of `images`; those podman refuses to remove are
+    /// left out of the report.
+    pub async fn remove_images(&self, images: &[ImageInfo]) -> Result<ImagePruneReport> {
+        let mut report = ImagePruneReport::default();
+        for image in images {
+            if self.image_remove(&image.id).await? {
+                report.bytes_reclaimed += image.size;
+                report
+                    .removed
+                    .push(image.names.first().unwrap_or(&image.id).clone());
+            }
+        }
+        Ok(report)
+    }
+
+    /// `podman rmi image` without `-f`. Returns false, after logging why, if podman
+    /// refuses (e.g. a container still uses the image).
+    pub async fn image_remove(&self, image: &str) -> Result<bool> {
+        let r = self
+            .run_capture_allow_fail(["rmi", image].as_slice(), Some(Duration::from_secs(60)))
+            .await?;
+        if r.exit_code != 0 {
+            warn!(
+                image = %image,
+                error = %trunc_utf8_lossy(&r.stderr, 1024).trim(),
+                event = "image_prune_skipped"
+            );
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
     pub async fn volume_created_at(&self, name: &str) -> Result<Option<DateTime<Utc>>> {
         let info = self.volume_inspect_info(name).await?;
         Ok(info.created_at)
@@ -492,18 +1199,33 @@ impl Podman {
         Ok(())
     }
 
-    pub async fn build_image(
-        &self,
-        context_dir: &std::path::Path,
-        containerfile_path: &std::path::Path,
-        tag: &str,
-        pull: bool,
-        no_cache: bool,
-    ) -> Result<()> {
+    /// `podman pull image`; fails (with the usual [`PodmanRunError`] classification,
+    /// e.g. `AuthRequired`) if the pull does.
+    pub async fn pull(&self, image: &str) -> Result<()> {
+        self.run_capture(["pull", "--quiet", image].as_slice(), None)
+            .await
+            .with_context(|| format!("pull {image}"))?;
+        Ok(())
+    }
+
+    /// Build `tag` from `containerfile_path`. With `progress`, build output is also
+    /// parsed into [`PodmanEvent::BuildStep`] events for the observer, and all of it
+    /// goes to stderr.
+    pub async fn build_image(&self, build: ImageBuild<'_>) -> Result<()> {
+        let ImageBuild {
+            context_dir,
+            containerfile_path,
+            tag,
+            podci_version,
+            pull,
+            no_cache,
+            progress,
+        } = build;
         let mut args: Vec<String> = Vec::new();
         args.push("build".to_string());
-        if pull {
-            args.push("--pull".to_string());
+        // `missing` is podman's default; leave the flag off so the argv stays as before.
+        if pull != PullPolicy::Missing {
+            args.push(format!("--pull={}", pull.as_str()));
         }
         if no_cache {
             args.push("--no-cache".to_string());
@@ -512,20 +1234,171 @@ impl Podman {
         args.push(containerfile_path.display().to_string());
         args.push("-t".to_string());
         args.push(tag.to_string());
+        args.push("--label".to_string());
+        args.push(format!("{}={}", MANAGED_LABEL.0, MANAGED_LABEL.1));
+        args.push("--label".to_string());
+        args.push(format!("{VERSION_LABEL}={podci_version}"));
         args.push(context_dir.display().to_string());
 
         let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
-        let _ = self
-            .run_inherit(arg_refs.as_slice(), &[], None, None)
-            .await?;
+        if progress {
+            self.run_build_with_progress(arg_refs.as_slice()).await?;
+        } else {
+            let _ = self
+                .run_inherit(arg_refs.as_slice(), &[], None, None)
+                .await?;
+        }
         Ok(())
     }
 
+    /// Run a `podman build`, passing every output line through unchanged (to stderr, so
+    /// stdout stays free for machine-readable output) while also emitting parsed steps
+    /// as [`PodmanEvent::BuildStep`]. Lines that don't parse are simply passed through,
+    /// so nothing is lost if podman's format changes.
+    async fn run_build_with_progress(&self, args: &[&str]) -> Result<()> {
+        use std::io::Write;
+        use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+        let mut cmd = Command::new(&self.path);
+        cmd.args(args);
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        cmd.kill_on_drop(true);
+
+        let start = Instant::now();
+        self.emit_start(args);
+
+        type Stream<'a> = Box<dyn AsyncRead + Unpin + Send + 'a>;
+        let replayed = self.replayed(args)?;
+        let mut child = None;
+        let (out, err): (Stream, Stream) = match &replayed {
+            Some(r) => (Box::new(r.stdout.as_slice()), Box::new(r.stderr.as_slice())),
+            None => {
+                let c = child.insert(cmd.spawn().context("spawn podman")?);
+                (
+                    Box::new(c.stdout.take().context("podman stdout not piped")?),
+                    Box::new(c.stderr.take().context("podman stderr not piped")?),
+                )
+            }
+        };
+        let (mut out, mut err) = (BufReader::new(out), BufReader::new(err));
+
+        let mut parser = BuildProgressParser::default();
+        let mut term_err = std::io::stderr();
+        let (mut out_line, mut err_line) = (Vec::new(), Vec::new());
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let (mut out_done, mut err_done) = (false, false);
+        while !(out_done && err_done) {
+            tokio::select! {
+                n = out.read_until(b'\n', &mut out_line), if !out_done => {
+                    if n? == 0 {
+                        out_done = true;
+                        continue;
+                    }
+                    term_err.write_all(&out_line)?;
+                    term_err.flush()?;
+                    if let Some(step) = parser.push_line(&String::from_utf8_lossy(&out_line)) {
+                        self.emit_build_step(step);
+                    }
+                    stdout.extend_from_slice(&out_line);
+                    out_line.clear();
+                }
+                n = err.read_until(b'\n', &mut err_line), if !err_done => {
+                    if n? == 0 {
+                        err_done = true;
+                        continue;
+                    }
+                    term_err.write_all(&err_line)?;
+                    term_err.flush()?;
+                    if let Some(step) = parser.push_line(&String::from_utf8_lossy(&err_line)) {
+                        self.emit_build_step(step);
+                    }
+                    stderr.extend_from_slice(&err_line);
+                    err_line.clear();
+                }
+            }
+        }
+        let status = match (&mut child, &replayed) {
+            (Some(c), _) => c.wait().await?,
+            (None, Some(r)) => r.status,
+            (None, None) => unreachable!("build neither spawned nor replayed"),
+        };
+        if let Some(step) = parser.finish() {
+            self.emit_build_step(step);
+        }
+
+        let duration = start.elapsed();
+        let exit_code = status.code().unwrap_or(1);
+        self.emit_exit(args, exit_code, duration, &stdout, &stderr);
+
+        if !status.success() {
+            let err = PodmanRunError::from_exec(
+                format_cmd(&self.path, args),
+                exit_code,
+                &[],
+                &stderr,
+                None,
+                None,
+            );
+            return Err(anyhow::Error::new(err));
+        }
+        Ok(())
+    }
+
+    /// Raw version line (e.g. `podman version 5.2.0`). Cached; see [`Podman::version_info`].
     pub async fn version(&self) -> Result<String> {
+        Ok(self.version_info().await?.raw)
+    }
+
+    /// Structured podman version, cached per binary path for the process lifetime.
+    ///
+    /// Prefers `podman version --format json`; old podman without JSON support falls
+    /// back to parsing `podman --version`.
+    pub async fn version_info(&self) -> Result<PodmanVersion> {
+        if let Some(v) = version_cache().lock().unwrap().get(&self.path) {
+            return Ok(v.clone());
+        }
+
+        let json = self
+            .run_capture(
+                ["version", "--format", "json"].as_slice(),
+                Some(Duration::from_secs(10)),
+            )
+            .await
+            .ok()
+            .and_then(|r| parse_version_json(&r.stdout));
+        let v = match json {
+            Some(v) => v,
+            None => {
+                let r = self
+                    .run_capture(["--version"].as_slice(), Some(Duration::from_secs(10)))
+                    .await?;
+                parse_version_line(&String::from_utf8_lossy(&r.stdout))
+            }
+        };
+
+        version_cache()
+            .lock()
+            .unwrap()
+            .insert(self.path.clone(), v.clone());
+        Ok(v)
+    }
+
+    /// Inspect a remote image manifest (or manifest list) without pulling it.
+    ///
+    /// Wraps `podman manifest inspect <ref>`; registry errors (including auth
+    /// failures) are classified like any other podman failure.
+    pub async fn manifest_inspect_json(&self, image: &str) -> Result<serde_json::Value> {
         let r = self
-            .run_capture(["--version"].as_slice(), Some(Duration::from_secs(10)))
+            .run_capture(
+                ["manifest", "inspect", image].as_slice(),
+                Some(Duration::from_secs(60)),
+            )
             .await?;
-        Ok(String::from_utf8_lossy(&r.stdout).trim().to_string())
+        let v: serde_json::Value =
+            serde_json::from_slice(&r.stdout).context("parse podman manifest inspect json")?;
+        Ok(v)
     }
 
     pub async fn info_json(&self) -> Result<serde_json::Value> {
@@ -575,6 +1448,14 @@ impl Podman {
     }
 }
 
+/// Exit code recorded for a command stopped by its timeout (as `timeout(1)` reports).
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// True if `err` comes from a podman invocation that exceeded its timeout.
+pub fn is_timeout(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<tokio::time::error::Elapsed>().is_some()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ImageDigestStatus {
     Present(String),
@@ -582,6 +1463,66 @@ pub enum ImageDigestStatus {
     Error(String),
 }
 
+/// First field of `du -sb` output (`<bytes>\t<path>`).
+fn parse_du_bytes(stdout: &[u8]) -> Result<u64> {
+    let out = String::from_utf8_lossy(stdout);
+    let Some(bytes) = out.split_whitespace().next() else {
+        bail!("du printed nothing");
+    };
+    bytes
+        .parse()
+        .with_context(|| format!("unexpected du output {bytes:?}"))
+}
+
+fn parse_images_json(stdout: &[u8]) -> Result<Vec<ImageInfo>> {
+    #[derive(Deserialize)]
+    struct ImageRow {
+        #[serde(rename = "Id")]
+        id: String,
+        #[serde(rename = "Names", default)]
+        names: Option<Vec<String>>,
+        #[serde(rename = "Digest", default)]
+        digest: Option<String>,
+        #[serde(rename = "Size", default)]
+        size: u64,
+        /// Unix seconds.
+        #[serde(rename = "Created", default)]
+        created: Option<i64>,
+        #[serde(rename = "Labels", default)]
+        labels: Option<std::collections::BTreeMap<String, String>>,
+    }
+
+    let rows: Vec<ImageRow> = serde_json::from_slice(stdout).context("parse podman images json")?;
+    Ok(rows
+        .into_iter()
+        .map(|r| ImageInfo {
+            id: r.id,
+            names: r.names.unwrap_or_default(),
+            digest: r.digest.filter(|d| !d.is_empty()),
+            size: r.size,
+            created: r.created.and_then(|t| DateTime::from_timestamp(t, 0)),
+            labels: r.labels.unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Template images from `images` that `keep` refers to by neither id, digest, nor name.
+/// Images without a `localhost/podci-` name are never selected, labels notwithstanding.
+pub fn select_unused_images(
+    images: Vec<ImageInfo>,
+    keep: &std::collections::BTreeSet<String>,
+) -> Vec<ImageInfo> {
+    images
+        .into_iter()
+        .filter(|i| i.names.iter().any(|n| n.starts_with(TEMPLATE_IMAGE_PREFIX)))
+        .filter(|i| {
+            !(keep.contains(&i.id)
+                || i.digest.as_ref().is_some_and(|d| keep.contains(d))
+                || i.names.iter().any(|n| keep.contains(n)))
+        })
+        .collect()
+}
+
 fn trunc_utf8_lossy(bytes: &[u8], max_len: usize) -> String {
     if bytes.len() <= max_len {
         return String::from_utf8_lossy(bytes).to_string();
@@ -596,6 +1537,14 @@ fn trunc_utf8_lossy(bytes: &[u8], max_len: usize) -> String {
 
 fn classify_failure(exit_code: i32, stderr: &[u8]) -> PodmanErrorKind {
     let s = String::from_utf8_lossy(stderr).to_lowercase();
+    // Registry auth failures must be checked first: their messages often also
+    // contain generic "denied" wording.
+    if s.contains("unauthorized")
+        || s.contains("authentication required")
+        || s.contains("requested access to the resource is denied")
+    {
+        return PodmanErrorKind::AuthRequired;
+    }
     if s.contains("permission denied") {
         return PodmanErrorKind::PermissionDenied;
     }
@@ -608,6 +1557,177 @@ fn classify_failure(exit_code: i32, stderr: &[u8]) -> PodmanErrorKind {
     PodmanErrorKind::CommandFailed
 }
 
+fn version_cache() -> &'static std::sync::Mutex<std::collections::HashMap<PathBuf, PodmanVersion>> {
+    static CACHE: std::sync::OnceLock<
+        std::sync::Mutex<std::collections::HashMap<PathBuf, PodmanVersion>>,
+    > = std::sync::OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn parse_version_json(stdout: &[u8]) -> Option<PodmanVersion> {
+    let v: serde_json::Value = serde_json::from_slice(stdout).ok()?;
+    let client = v.get("Client")?;
+    let client_version = client.get("Version")?.as_str()?.to_string();
+    let api_version = client
+        .get("APIVersion")
+        .and_then(|a| a.as_str())
+        .map(|a| a.to_string());
+    Some(PodmanVersion {
+        raw: format!("podman version {client_version}"),
+        client_version,
+        api_version,
+    })
+}
+
+fn parse_version_line(line: &str) -> PodmanVersion {
+    let raw = line.trim().to_string();
+    let client_version = raw
+        .split_whitespace()
+        .last()
+        .unwrap_or_default()
+        .to_string();
+    PodmanVersion {
+        client_version,
+        api_version: None,
+        raw,
+    }
+}
+
+/// Read `child_out` / `child_err` line by line, passing each line to `on_line` and
+/// collecting the raw bytes.
+async fn stream_lines<O, E>(
+    child_out: O,
+    child_err: E,
+    on_line: &mut (dyn FnMut(OutputStream, &[u8]) + Send),
+) -> Result<(Vec<u8>, Vec<u8>)>
+where
+    O: tokio::io::AsyncRead + Unpin,
+    E: tokio::io::AsyncRead + Unpin,
+{
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let mut out_reader = BufReader::new(child_out);
+    let mut err_reader = BufReader::new(child_err);
+    let (mut out_line, mut err_line) = (Vec::new(), Vec::new());
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let (mut out_done, mut err_done) = (false, false);
+
+    while !(out_done && err_done) {
+        tokio::select! {
+            n = out_reader.read_until(b'\n', &mut out_line), if !out_done => {
+                // A read cancelled by the other branch leaves its bytes in the line
+                // buffer, so EOF can still come with an unterminated line pending.
+                if n? == 0 {
+                    out_done = true;
+                    if out_line.is_empty() {
+                        continue;
+                    }
+                }
+                on_line(OutputStream::Stdout, &out_line);
+                stdout.append(&mut out_line);
+            }
+            n = err_reader.read_until(b'\n', &mut err_line), if !err_done => {
+                // A read cancelled by the other branch leaves its bytes in the line
+                // buffer, so EOF can still come with an unterminated line pending.
+                if n? == 0 {
+                    err_done = true;
+                    if err_line.is_empty() {
+                        continue;
+                    }
+                }
+                on_line(OutputStream::Stderr, &err_line);
+                stderr.append(&mut err_line);
+            }
+        }
+    }
+    Ok((stdout, stderr))
+}
+
+/// Copy child stdout/stderr to the terminal and `log` until both reach EOF,
+/// returning the captured bytes of each stream.
+async fn tee_streams<O, E>(
+    mut child_out: O,
+    mut child_err: E,
+    log: &mut tokio::fs::File,
+) -> Result<(Vec<u8>, Vec<u8>)>
+where
+    O: tokio::io::AsyncRead + Unpin,
+    E: tokio::io::AsyncRead + Unpin,
+{
+    use std::io::Write;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Terminal writes are small and line-sized; blocking std handles are fine here.
+    let mut term_out = std::io::stdout();
+    let mut term_err = std::io::stderr();
+    let mut out_buf = [0u8; 8192];
+    let mut err_buf = [0u8; 8192];
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let (mut out_done, mut err_done) = (false, false);
+
+    while !(out_done && err_done) {
+        tokio::select! {
+            n = child_out.read(&mut out_buf), if !out_done => {
+                let n = n?;
+                if n == 0 {
+                    out_done = true;
+                    continue;
+                }
+                term_out.write_all(&out_buf[..n])?;
+                term_out.flush()?;
+                log.write_all(&out_buf[..n]).await?;
+                stdout.extend_from_slice(&out_buf[..n]);
+            }
+            n = child_err.read(&mut err_buf), if !err_done => {
+                let n = n?;
+                if n == 0 {
+                    err_done = true;
+                    continue;
+                }
+                term_err.write_all(&err_buf[..n])?;
+                term_err.flush()?;
+                log.write_all(&err_buf[..n]).await?;
+                stderr.extend_from_slice(&err_buf[..n]);
+            }
+        }
+    }
+    log.flush().await?;
+    Ok((stdout, stderr))
+}
+
+/// Args as recorded in a trace: `--env`/`-e` values are replaced by `***`.
+fn trace_args(args: &[&str]) -> Vec<String> {
+    let mask = |kv: &str| match kv.split_once('=') {
+        Some((k, _)) => format!("{k}=***"),
+        None => kv.to_string(),
+    };
+    let mut out = Vec::with_capacity(args.len());
+    let mut env_value = false;
+    for a in args {
+        if env_value {
+            out.push(mask(a));
+            env_value = false;
+        } else if let Some(kv) = a.strip_prefix("--env=") {
+            out.push(format!("--env={}", mask(kv)));
+        } else {
+            env_value = matches!(*a, "--env" | "-e");
+            out.push(a.to_string());
+        }
+    }
+    out
+}
+
+/// Show replayed output where an inherited-stdio invocation would have printed it.
+fn replay_to_terminal(out: &std::process::Output) -> Result<()> {
+    use std::io::Write;
+
+    std::io::stdout().write_all(&out.stdout)?;
+    std::io::stderr().write_all(&out.stderr)?;
+    Ok(())
+}
+
 fn format_cmd(bin: &std::path::Path, args: &[&str]) -> String {
     let mut s = String::new();
     s.push_str(bin.to_string_lossy().as_ref());
@@ -626,7 +1746,78 @@ struct _PodmanInfoMinimal {
 
 #[cfg(test)]
 mod tests {
-    use super::{trunc_utf8_lossy, PodmanRunError};
+    use super::{
+        classify_failure, parse_version_json, parse_version_line, trace_args, trunc_utf8_lossy,
+        Podman, PodmanErrorKind, PodmanRunError, TraceEntry,
+    };
+
+    #[test]
+    fn images_json_parses_names_digests_and_labels() {
+        use super::parse_images_json;
+
+        let images = parse_images_json(
+            br#"[
+                {"Id": "a1", "Names": ["localhost/podci-rust-debian:v1"], "Digest": "sha256:aa", "Size": 100,
+                 "Created": 1767225600, "Labels": {"podci.managed": "true", "podci.version": "0.1.0"}},
+                {"Id": "b2", "Names": ["localhost/podci-rust-debian:v2"], "Digest": "sha256:bb", "Size": 200},
+                {"Id": "c3", "Names": ["localhost/podci-rust-alpine:v1"], "Digest": "sha256:cc", "Size": 300},
+                {"Id": "d4", "Names": ["docker.io/library/ubuntu:24.04"], "Digest": "sha256:dd", "Size": 400},
+                {"Id": "e5", "Names": null, "Digest": "", "Size": 500}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(images[4].names, Vec::<String>::new());
+        assert_eq!(images[4].digest, None);
+        assert_eq!(images[4].created, None);
+        assert_eq!(
+            images[0]
+                .labels
+                .get(super::VERSION_LABEL)
+                .map(String::as_str),
+            Some("0.1.0")
+        );
+        assert_eq!(
+            images[0].created.map(|c| c.to_rfc3339()).as_deref(),
+            Some("2026-01-01T00:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn unused_image_selection_keeps_referenced_and_foreign_images() {
+        use super::{parse_images_json, select_unused_images};
+
+        let images = parse_images_json(
+            br#"[
+                {"Id": "a1", "Names": ["localhost/podci-rust-debian:v1"], "Digest": "sha256:aa", "Size": 100},
+                {"Id": "b2", "Names": ["localhost/podci-rust-debian:v2"], "Digest": "sha256:bb", "Size": 200},
+                {"Id": "c3", "Names": ["localhost/podci-rust-alpine:v1"], "Digest": "sha256:cc", "Size": 300},
+                {"Id": "d4", "Names": ["docker.io/library/ubuntu:24.04"], "Digest": "sha256:dd", "Size": 400},
+                {"Id": "e5", "Names": null, "Digest": "", "Size": 500}
+            ]"#,
+        )
+        .unwrap();
+        let keep = ["sha256:aa", "localhost/podci-rust-alpine:v1"]
+            .map(String::from)
+            .into();
+        let ids: Vec<String> = select_unused_images(images, &keep)
+            .into_iter()
+            .map(|i| i.id)
+            .collect();
+        assert_eq!(ids, ["b2"]);
+    }
+
+    #[test]
+    fn du_output_parses_to_bytes() {
+        use super::parse_du_bytes;
+
+        assert_eq!(
+            parse_du_bytes(b"123456\t/home/u/.local/share/containers/storage/volumes/v/_data\n")
+                .unwrap(),
+            123456
+        );
+        assert!(parse_du_bytes(b"").is_err());
+        assert!(parse_du_bytes(b"du: cannot read\n").is_err());
+    }
 
     #[test]
     fn trunc_utf8_lossy_returns_full_when_short() {
@@ -657,4 +1848,285 @@ mod tests {
         assert!(s.contains("stderr: /tmp/stderr.log"));
         assert!(s.contains("stdout: /tmp/stdout.log"));
     }
+
+    #[test]
+    fn classify_failure_detects_registry_auth_errors() {
+        let kind = classify_failure(
+            125,
+            b"Error: reading manifest latest in ghcr.io/org/img: unauthorized: authentication required",
+        );
+        assert!(matches!(kind, PodmanErrorKind::AuthRequired));
+    }
+
+    #[tokio::test]
+    async fn trace_records_invocations_and_replay_feeds_them_back() {
+        let dir = std::env::temp_dir().join(format!("podci-trace-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let trace = dir.join("trace.jsonl");
+
+        // `sh -c <script> -e KEY=VALUE`: the trailing words only land in $0/$1.
+        let script = "echo out; echo err >&2; exit 3";
+        let args = ["-c", script, "-e", "TOKEN=hunter2"];
+        let sh = Podman::new("/bin/sh".into()).with_trace(&trace).unwrap();
+        let live = sh.run_capture_allow_failure(&args, None).await.unwrap();
+        assert_eq!(live.exit_code, 3);
+
+        let raw = std::fs::read_to_string(&trace).unwrap();
+        assert!(!raw.contains("hunter2"), "{raw}");
+        let entry: TraceEntry = serde_json::from_str(raw.lines().next().unwrap()).unwrap();
+        assert_eq!(entry.args, ["-c", script, "-e", "TOKEN=***"]);
+        assert_eq!(
+            (entry.stdout.as_str(), entry.stderr.as_str()),
+            ("out\n", "err\n")
+        );
+
+        // Replay never spawns the (nonexistent) binary.
+        let replay = Podman::new(dir.join("no-such-podman"))
+            .with_replay(&trace)
+            .unwrap();
+        let r = replay.run_capture_allow_failure(&args, None).await.unwrap();
+        assert_eq!(
+            (r.exit_code, r.stdout, r.stderr),
+            (3, live.stdout, live.stderr)
+        );
+        let err = replay.run_capture(&args, None).await.unwrap_err();
+        assert!(err.to_string().contains("no entry left"), "{err}");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn trace_args_mask_env_values() {
+        assert_eq!(
+            trace_args(&["run", "--env", "A=1", "--env=B=2", "-e", "C", "img", "A=1"]),
+            [
+                "run",
+                "--env",
+                "A=***",
+                "--env=B=***",
+                "-e",
+                "C",
+                "img",
+                "A=1"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn run_inherit_tee_captures_and_writes_log() {
+        let dir = std::env::temp_dir().join(format!("podci-tee-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("out.log");
+
+        // Stand in a shell for podman: the tee path doesn't care what the binary is.
+        let sh = Podman::new("/bin/sh".into());
+        let r = sh
+            .run_inherit_tee(&["-c", "echo out; echo err >&2"], &[], None, None, &log)
+            .await
+            .unwrap();
+        assert_eq!(r.stdout, b"out\n");
+        assert_eq!(r.stderr, b"err\n");
+
+        let logged = std::fs::read_to_string(&log).unwrap();
+        assert!(logged.contains("out\n"));
+        assert!(logged.contains("err\n"));
+
+        let err = sh
+            .run_inherit_tee(&["-c", "echo boom >&2; exit 3"], &[], None, None, &log)
+            .await
+            .unwrap_err();
+        let e = err.downcast_ref::<PodmanRunError>().unwrap();
+        assert_eq!(e.status, Some(3));
+        assert!(e.stderr_trunc.contains("boom"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn version_parses_json_and_falls_back_to_line() {
+        let json = br#"{"Client":{"APIVersion":"5.2.0","Version":"5.2.1","GoVersion":"go1.22"}}"#;
+        let v = parse_version_json(json).unwrap();
+        assert_eq!(v.client_version, "5.2.1");
+        assert_eq!(v.api_version.as_deref(), Some("5.2.0"));
+        assert_eq!(v.raw, "podman version 5.2.1");
+
+        assert!(parse_version_json(b"Error: unknown flag: --format").is_none());
+
+        let v = parse_version_line("podman version 3.4.4\n");
+        assert_eq!(v.client_version, "3.4.4");
+        assert_eq!(v.api_version, None);
+        assert_eq!(v.raw, "podman version 3.4.4");
+    }
+
+    #[tokio::test]
+    async fn timeouts_are_distinguishable_from_other_errors() {
+        let sh = Podman::new("/bin/sh".into());
+        let err = sh
+            .run_capture_allow_failure(
+                &["-c", "sleep 5"],
+                Some(std::time::Duration::from_millis(50)),
+            )
+            .await
+            .unwrap_err();
+        assert!(super::is_timeout(&err), "{err:#}");
+
+        let missing = Podman::new("/nonexistent/podman".into());
+        let err = missing
+            .run_capture_allow_failure(&["version"], Some(std::time::Duration::from_secs(5)))
+            .await
+            .unwrap_err();
+        assert!(!super::is_timeout(&err));
+    }
+
+    #[tokio::test]
+    async fn observer_receives_start_and_exit() {
+        use super::{PodmanEvent, PodmanObserver};
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Debug, Default)]
+        struct Recorder(Mutex<Vec<String>>);
+        impl PodmanObserver for Recorder {
+            fn on_event(&self, event: &PodmanEvent) {
+                let line = match event {
+                    PodmanEvent::Start { .. } => "start".to_string(),
+                    PodmanEvent::Exit { exit_code, .. } => format!("exit {exit_code}"),
+                    PodmanEvent::BuildStep(b) => format!("step {}", b.step),
+                };
+                self.0.lock().unwrap().push(line);
+            }
+        }
+
+        let rec = Arc::new(Recorder::default());
+        let sh = Podman::new("/bin/sh".into()).with_observer(rec.clone());
+        let r = sh
+            .run_capture_allow_failure(&["-c", "exit 7"], None)
+            .await
+            .unwrap();
+        assert_eq!(r.exit_code, 7);
+        assert_eq!(*rec.0.lock().unwrap(), vec!["start", "exit 7"]);
+    }
+
+    #[test]
+    fn build_progress_parser_tracks_steps_and_cache_hits() {
+        use super::{BuildProgressParser, BuildStep};
+
+        let mut p = BuildProgressParser::default();
+        let lines = [
+            "STEP 1/3: FROM registry.fedoraproject.org/fedora:40",
+            "STEP 2/3: RUN dnf -y install gcc",
+            "--> Using cache 0123abcd",
+            "--> 0123abcd",
+            "[2/2] STEP 3/3: COPY --from=build /out /out",
+            "some unparseable noise",
+            "COMMIT localhost/podci-rust:latest",
+        ];
+        let mut steps: Vec<BuildStep> = lines.iter().filter_map(|l| p.push_line(l)).collect();
+        steps.extend(p.finish());
+
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].step, 1);
+        assert_eq!(steps[0].total, Some(3));
+        assert!(!steps[0].cached);
+        assert_eq!(steps[1].instruction, "RUN dnf -y install gcc");
+        assert!(steps[1].cached);
+        assert_eq!(steps[2].instruction, "COPY --from=build /out /out");
+        assert!(p.finish().is_none());
+
+        // Older buildah omits the total; docker spells it `Step N/M :`.
+        assert_eq!(p.push_line("STEP 4: RUN true"), None);
+        let s = p.push_line("Step 5/6 : RUN false").unwrap();
+        assert_eq!((s.step, s.total), (4, None));
+        assert_eq!(p.finish().unwrap().total, Some(6));
+    }
+
+    #[tokio::test]
+    async fn streaming_capture_matches_buffered_capture() {
+        use super::OutputStream;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("podci-streaming-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let fake = dir.join("podman");
+        std::fs::write(
+            &fake,
+            "#!/bin/sh\necho one\necho oops >&2\nprintf 'two\\nno-newline'\nexit 3\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let podman = Podman::new(fake);
+
+        let mut lines = Vec::new();
+        let streamed = podman
+            .run_capture_streaming(&["run"], &[], None, None, &mut |stream, line| {
+                lines.push((stream, String::from_utf8_lossy(line).into_owned()))
+            })
+            .await
+            .unwrap();
+        let buffered = podman
+            .run_capture_with_env_allow_failure(&["run"], &[], None, None)
+            .await
+            .unwrap();
+        assert_eq!(streamed.exit_code, 3);
+        assert_eq!(streamed.exit_code, buffered.exit_code);
+        assert_eq!(streamed.stdout, buffered.stdout);
+        assert_eq!(streamed.stderr, buffered.stderr);
+
+        let stdout: Vec<&str> = lines
+            .iter()
+            .filter(|(st, _)| *st == OutputStream::Stdout)
+            .map(|(_, l)| l.as_str())
+            .collect();
+        assert_eq!(stdout, ["one\n", "two\n", "no-newline"]);
+        assert!(lines.contains(&(OutputStream::Stderr, "oops\n".to_string())));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn build_image_with_progress_emits_build_steps() {
+        use super::{ImageBuild, PodmanEvent, PodmanObserver, PullPolicy};
+        use std::os::unix::fs::PermissionsExt;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Debug, Default)]
+        struct Steps(Mutex<Vec<(u32, bool)>>);
+        impl PodmanObserver for Steps {
+            fn on_event(&self, event: &PodmanEvent) {
+                if let PodmanEvent::BuildStep(b) = event {
+                    self.0.lock().unwrap().push((b.step, b.cached));
+                }
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("podci-build-progress-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let fake = dir.join("podman");
+        std::fs::write(
+            &fake,
+            "#!/bin/sh\necho 'STEP 1/2: FROM fedora'\necho 'STEP 2/2: RUN true'\n\
+             echo '--> Using cache abc'\necho 'warning: noise' >&2\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let rec = Arc::new(Steps::default());
+        let podman = Podman::new(fake).with_observer(rec.clone());
+        podman
+            .build_image(ImageBuild {
+                context_dir: &dir,
+                containerfile_path: &dir.join("Containerfile"),
+                tag: "t",
+                podci_version: "0.0.0",
+                pull: PullPolicy::Missing,
+                no_cache: false,
+                progress: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(*rec.0.lock().unwrap(), vec![(1, false), (2, true)]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
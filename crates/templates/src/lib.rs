@@ -140,6 +140,31 @@ pub fn resolve_template(roots: &[PathBuf], name: &str) -> Result<TemplateEntry>
     bail!("unknown template '{name}'. Use 'podci templates list' to see available templates.")
 }
 
+/// Relative paths that `init_template` would write for `name`, sorted.
+///
+/// Read-only: nothing is written and the destination is not inspected.
+pub fn template_files(roots: &[PathBuf], name: &str) -> Result<Vec<PathBuf>> {
+    let entry = resolve_template(roots, name)?;
+    match entry.origin {
+        TemplateOrigin::Disk(dir) => {
+            let files_root = dir.join("files");
+            if !files_root.is_dir() {
+                bail!(
+                    "template '{name}' is missing files/ directory: {}",
+                    files_root.display()
+                );
+            }
+            let mut out = Vec::new();
+            for (rel, _) in collect_files_sorted(&files_root)? {
+                ensure_safe_rel_path(&rel)?;
+                out.push(rel);
+            }
+            Ok(out)
+        }
+        TemplateOrigin::Embedded => Ok(vec![PathBuf::from("podci.toml")]),
+    }
+}
+
 /// Initialize a directory from a named template.
 ///
 /// Safety rules:
@@ -191,6 +216,76 @@ pub async fn init_template(
     Ok(())
 }
 
+/// Template payload keyed by bundle path (`template.toml`, `files/<rel>`), as in the
+/// export layout.
+fn template_payload(roots: &[PathBuf], name: &str) -> Result<BTreeMap<String, Vec<u8>>> {
+    let entry = resolve_template(roots, name)?;
+    let mut out = BTreeMap::new();
+    match entry.origin {
+        TemplateOrigin::Disk(dir) => {
+            let meta_path = dir.join("template.toml");
+            let meta = std::fs::read(&meta_path)
+                .with_context(|| format!("read {}", meta_path.display()))?;
+            out.insert("template.toml".to_string(), meta);
+            for rel in template_files(roots, name)? {
+                let abs = dir.join("files").join(&rel);
+                let bytes =
+                    std::fs::read(&abs).with_context(|| format!("read {}", abs.display()))?;
+                out.insert(format!("files/{}", rel.display()), bytes);
+            }
+        }
+        TemplateOrigin::Embedded => {
+            out.insert(
+                "template.toml".to_string(),
+                GENERIC_TEMPLATE_TOML.as_bytes().to_vec(),
+            );
+            out.insert(
+                "files/podci.toml".to_string(),
+                GENERIC_PODCI_TOML.as_bytes().to_vec(),
+            );
+        }
+    }
+    Ok(out)
+}
+
+/// Compare two templates' `template.toml` and files.
+///
+/// Default output is a unified diff per differing file plus `Only in <name>: <path>`
+/// lines. With `name_only`, each differing path is listed as `M <path>` (both, differs),
+/// `D <path>` (only in `a`), or `A <path>` (only in `b`). Empty when identical.
+pub fn diff_templates(roots: &[PathBuf], a: &str, b: &str, name_only: bool) -> Result<String> {
+    let left = template_payload(roots, a)?;
+    let right = template_payload(roots, b)?;
+    let paths: std::collections::BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+
+    let mut out = String::new();
+    for path in paths {
+        match (left.get(path), right.get(path)) {
+            (Some(l), Some(r)) if l == r => {}
+            (Some(l), Some(r)) => {
+                if name_only {
+                    out.push_str(&format!("M {path}\n"));
+                    continue;
+                }
+                let (l, r) = (String::from_utf8_lossy(l), String::from_utf8_lossy(r));
+                let diff = similar::TextDiff::from_lines(l.as_ref(), r.as_ref());
+                out.push_str(
+                    &diff
+                        .unified_diff()
+                        .header(&format!("{a}/{path}"), &format!("{b}/{path}"))
+                        .to_string(),
+                );
+            }
+            (Some(_), None) if name_only => out.push_str(&format!("D {path}\n")),
+            (Some(_), None) => out.push_str(&format!("Only in {a}: {path}\n")),
+            (None, Some(_)) if name_only => out.push_str(&format!("A {path}\n")),
+            (None, Some(_)) => out.push_str(&format!("Only in {b}: {path}\n")),
+            (None, None) => {}
+        }
+    }
+    Ok(out)
+}
+
 /// Export a template as a deterministic `.tar.gz` stream.
 ///
 /// Note: the CLI writes bundles to a file; this function supports generic writers for testing and internal use.
@@ -415,6 +510,14 @@ const CONTAINERFILE_CPP_DEBIAN: &str =
 const CONTAINERFILE_KDE_MIXED_DEBIAN: &str =
     include_str!("../templates/containerfiles/Containerfile.kde-mixed-debian");
 
+/// Names of the built-in template images (accepted by [`containerfile_for`]), sorted.
+pub const CONTAINERFILE_NAMES: &[&str] = &[
+    "cpp-debian",
+    "kde-mixed-debian",
+    "rust-alpine",
+    "rust-debian",
+];
+
 pub fn containerfile_for(platform: &str) -> Result<&'static str> {
     match platform {
         "rust-alpine" => Ok(CONTAINERFILE_RUST_ALPINE),
@@ -53,6 +53,39 @@ fn disk_template_beats_embedded_generic() {
     let _ = std::fs::remove_dir_all(&root);
 }
 
+#[test]
+fn template_files_lists_embedded_generic() {
+    let roots: Vec<PathBuf> = vec![PathBuf::from("/this/does/not/exist")];
+    assert_eq!(
+        template_files(&roots, "generic").unwrap(),
+        vec![PathBuf::from("podci.toml")]
+    );
+}
+
+#[test]
+fn template_files_lists_nested_disk_files_sorted() {
+    let root =
+        std::env::temp_dir().join(format!("podci-template-files-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let files = root.join("generic").join("files");
+    std::fs::create_dir_all(files.join(".github")).unwrap();
+    std::fs::write(
+        root.join("generic").join("template.toml"),
+        "name = \"generic\"\n",
+    )
+    .unwrap();
+    std::fs::write(files.join("podci.toml"), "project=\"REPLACE_ME\"\n").unwrap();
+    std::fs::write(files.join(".github").join("ci.yml"), "on: push\n").unwrap();
+
+    let roots = vec![root.clone()];
+    assert_eq!(
+        template_files(&roots, "generic").unwrap(),
+        vec![PathBuf::from(".github/ci.yml"), PathBuf::from("podci.toml")]
+    );
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
 #[test]
 fn init_refuses_non_empty_dir() {
     let roots: Vec<PathBuf> = vec![];
@@ -120,3 +153,52 @@ fn export_to_path_refuses_overwrite_and_creates_file() {
 
     let _ = std::fs::remove_dir_all(&dir);
 }
+
+#[test]
+fn diff_templates_reports_changed_added_and_removed_files() {
+    let root = std::env::temp_dir().join(format!("podci-templates-diff-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let write = |rel: &str, body: &str| {
+        let p = root.join(rel);
+        std::fs::create_dir_all(p.parent().unwrap()).unwrap();
+        std::fs::write(p, body).unwrap();
+    };
+    write("a/template.toml", "name = \"a\"\n");
+    write(
+        "a/files/podci.toml",
+        "version = 1\nproject = \"REPLACE_ME\"\n",
+    );
+    write("a/files/old.txt", "x\n");
+    write("b/template.toml", "name = \"b\"\n");
+    write("b/files/podci.toml", "version = 1\nproject = \"other\"\n");
+    write("b/files/new.txt", "y\n");
+
+    let roots = vec![root.clone()];
+    assert_eq!(
+        diff_templates(&roots, "a", "b", true).unwrap(),
+        "A files/new.txt\nD files/old.txt\nM files/podci.toml\nM template.toml\n"
+    );
+
+    let full = diff_templates(&roots, "a", "b", false).unwrap();
+    assert!(full.contains("--- a/files/podci.toml\n+++ b/files/podci.toml\n"));
+    assert!(full.contains("-project = \"REPLACE_ME\"\n+project = \"other\"\n"));
+    assert!(full.contains("Only in a: files/old.txt\n"));
+    assert!(full.contains("Only in b: files/new.txt\n"));
+
+    assert_eq!(diff_templates(&roots, "a", "a", false).unwrap(), "");
+    // Embedded generic participates like any other template.
+    assert!(diff_templates(&roots, "generic", "a", true)
+        .unwrap()
+        .contains("M files/podci.toml"));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn every_listed_containerfile_resolves() {
+    for name in CONTAINERFILE_NAMES {
+        let cf = containerfile_for(name).unwrap();
+        assert!(cf.contains("FROM "), "{name}");
+    }
+    assert!(containerfile_for("ubuntu").is_err());
+}